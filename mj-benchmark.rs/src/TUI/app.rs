@@ -9,11 +9,13 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use std::error::Error;
 use std::io;
+use std::panic::PanicHookInfo;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crossterm::{
+    cursor::Show,
     execute,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
     event::{KeyCode, KeyModifiers},
@@ -23,229 +25,93 @@ use ratatui::{
     Terminal,
 };
 
-use crate::{
-    collect_specs,
-    pts,
-    browser_bench,
-    csv_row,
-    google_auth,
-    google_sheets,
-    google_drive,
-};
+use crate::error::MjError;
+use crate::pipeline::{self, RunStart};
 
 use super::{
-    state::{TuiState, PipelineStep},
+    state::TuiState,
     ui::draw,
-    events::{poll_event, TuiEvent},
+    events::{is_ctrl_c, poll_event, TuiEvent},
 };
 
-pub async fn run_full_pipeline_with_tui(
-    sheet_id: &str,
-    drive_folder_id: &str,
-    csv_path: &str,
-    client_id: &str,
-    client_secret: &str,
-) -> Result<(), Box<dyn Error>> {
+pub async fn run_full_pipeline_with_tui(start: RunStart) -> Result<(), MjError> {
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen).map_err(terminal_error)?;
+    let _guard = TerminalGuard::enter();
+    terminal::enable_raw_mode().map_err(terminal_error)?;
 
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+    let mut terminal = Terminal::new(backend).map_err(terminal_error)?;
+    terminal.clear().map_err(terminal_error)?;
+
+    run_loop(&mut terminal, start).await
+}
+
+/// Puts the terminal back however the TUI ends: return, error or panic.
+/// Release builds abort on panic without unwinding, so the panic hook
+/// restores it as well.
+struct TerminalGuard {
+    previous_hook: PanicHook,
+}
+
+type PanicHook = Arc<dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static>;
+
+impl TerminalGuard {
+    fn enter() -> Self {
+        let previous_hook: PanicHook = Arc::from(std::panic::take_hook());
+        let previous = previous_hook.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            previous(info);
+        }));
+        TerminalGuard { previous_hook }
+    }
+}
 
-    let res = run_loop(
-        &mut terminal,
-        sheet_id,
-        drive_folder_id,
-        csv_path,
-        client_id,
-        client_secret,
-    )
-    .await;
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+        // The hook can't be replaced while panicking; ours still chains
+        // to the previous one then.
+        if !std::thread::panicking() {
+            let previous = self.previous_hook.clone();
+            std::panic::set_hook(Box::new(move |info| previous(info)));
+        }
+    }
+}
 
-    terminal::disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+/// Best effort: nothing useful can be done if the terminal is gone.
+fn restore_terminal() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+}
 
-    res
+fn terminal_error(e: io::Error) -> MjError {
+    MjError::Other(format!("terminal error: {}", e))
 }
 
 async fn run_loop<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
-    sheet_id: &str,
-    drive_folder_id: &str,
-    csv_path: &str,
-    client_id: &str,
-    client_secret: &str,
-) -> Result<(), Box<dyn Error>> {
+    start: RunStart,
+) -> Result<(), MjError> {
     let mut state = TuiState::new();
-    let platform = crate::platform::detect_platform();
     let tick_rate = Duration::from_millis(200);
-    let mut pipeline_done = false;
-
-    state.log(format!("Platform: {}", platform));
-
-    state.log("Authenticating with Google…");
-    let token = match google_auth::get_token(client_id, client_secret).await {
-        Ok(t) => t,
-        Err(e) => {
-            state.log(format!("ERROR: {}", e));
-            state.trigger_failure(PipelineStep::Specs, e.to_string());
-            state.set_step(PipelineStep::Done);
-            pipeline_done = true;
-            // Continue to UI loop so user can read error
-            loop {
-                terminal.draw(|f| draw(f, &state))?;
-                if let Some(TuiEvent::Key(KeyCode::Char('q') | KeyCode::Esc)) =
-                    poll_event(tick_rate)
-                {
-                    return Ok(());
-                }
-            }
-        }
-    };
 
-    state.start_step_timer();
+    let (handle, mut events, cancel) = pipeline::spawn(start);
 
     loop {
-        terminal.draw(|f| draw(f, &state))?;
-
-        if !pipeline_done {
-            match state.current_step {
-                PipelineStep::Specs => {
-                    state.log("Collecting device specs…");
-                    state.specs = Some(collect_specs(platform));
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Specs);
-
-                    state.set_step(PipelineStep::Pts);
-                    state.start_step_timer();
-                    state.reset_progress_bar();
-                }
-
-                PipelineStep::Pts => {
-                    state.log("Running PTS benchmarks…");
-
-                    let result: Result<(), Box<dyn Error>> = (|| {
-                        pts::ensure_pts_installed()?;
-                        pts::ensure_suite_exists()?;
-                        state.bench = Some(pts::run_suite()?);
-                        Ok(())
-                    })();
-
-                    if let Err(e) = result {
-                        state.log(format!("ERROR: {}", e));
-                        state.stop_step_timer();
-                        state.trigger_failure(PipelineStep::Pts, e.to_string());
-                        state.set_step(PipelineStep::Done);
-                        pipeline_done = true;
-                        continue;
-                    }
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Pts);
-
-                    state.set_step(PipelineStep::Browser);
-                    state.start_step_timer();
-                    state.reset_progress_bar();
-                }
-
-                PipelineStep::Browser => {
-                    state.log("Running browser benchmarks…");
-
-                    let result = browser_bench::run_browser_benchmarks().await;
-
-                    if let Some(b) = &mut state.bench {
-                        b.speedometer_score = result.speedometer;
-                        b.jetstream_score = result.jetstream;
-                        b.motionmark_score = result.motionmark;
-                    } else {
-                        let msg = "Missing benchmark state";
-                        state.log(format!("ERROR: {}", msg));
-                        state.stop_step_timer();
-                        state.trigger_failure(PipelineStep::Browser, msg);
-                        state.set_step(PipelineStep::Done);
-                        pipeline_done = true;
-                        continue;
-                    }
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Browser);
-
-                    state.set_step(PipelineStep::Csv);
-                    state.start_step_timer();
-                    state.reset_progress_bar();
-                }
-
-                PipelineStep::Csv => {
-                    state.log("Building CSV and syncing Google Sheets…");
-
-                    let result: Result<(), Box<dyn Error>> = (|| {
-                        let row = csv_row::build_csv_row(
-                            state.specs.as_ref().unwrap(),
-                            state.bench.as_ref().unwrap(),
-                        );
-                        csv_row::append_to_csv(csv_path, &row)?;
-                        google_sheets::append_row(sheet_id, &row, &token).await?;
-                        Ok(())
-                    })();
-
-                    if let Err(e) = result {
-                        state.log(format!("ERROR: {}", e));
-                        state.stop_step_timer();
-                        state.trigger_failure(PipelineStep::Csv, e.to_string());
-                        state.set_step(PipelineStep::Done);
-                        pipeline_done = true;
-                        continue;
-                    }
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Csv);
-
-                    state.set_step(PipelineStep::Sheets);
-                    state.start_step_timer();
-                    state.reset_progress_bar();
-                }
-
-                PipelineStep::Sheets => {
-                    state.log("Uploading CSV to Google Drive…");
-
-                    if let Err(e) =
-                        google_drive::upload_csv(drive_folder_id, csv_path, &token).await
-                    {
-                        state.log(format!("ERROR: {}", e));
-                        state.stop_step_timer();
-                        state.trigger_failure(PipelineStep::Sheets, e.to_string());
-                        state.set_step(PipelineStep::Done);
-                        pipeline_done = true;
-                        continue;
-                    }
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Sheets);
-
-                    state.set_step(PipelineStep::Drive);
-                    state.start_step_timer();
-                    state.reset_progress_bar();
-                }
-
-                PipelineStep::Drive => {
-                    state.log("Pipeline complete.");
-
-                    state.stop_step_timer();
-                    state.trigger_success(PipelineStep::Drive);
-
-                    state.set_step(PipelineStep::Done);
-                }
+        // Drain everything the engine produced since the last frame.
+        while let Ok(event) = events.try_recv() {
+            state.apply_event(event);
+        }
 
-                PipelineStep::Done => {
-                    pipeline_done = true;
-                }
-            }
+        // A cancelled run ends on its own once the step has stopped.
+        if state.cancelling && state.pipeline_finished {
+            return Err(MjError::Cancelled);
         }
 
+        terminal.draw(|f| draw(f, &state)).map_err(terminal_error)?;
+
         if let Some(ev) = poll_event(tick_rate) {
             match ev {
                 TuiEvent::Tick => {
@@ -258,8 +124,8 @@ async fn run_loop<B: ratatui::backend::Backend>(
                 }
 
                 TuiEvent::Key(key) => {
-                    if state.in_search_mode {
-                        match key {
+                    if state.in_search_mode && !is_ctrl_c(&key) {
+                        match key.code {
                             KeyCode::Esc => state.cancel_search(),
                             KeyCode::Enter => state.finalize_search(),
                             KeyCode::Backspace => state.pop_search_char(),
@@ -269,9 +135,27 @@ async fn run_loop<B: ratatui::backend::Backend>(
                         continue;
                     }
 
-                    match key {
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    let quit = is_ctrl_c(&key) || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc);
+                    if quit {
+                        if !state.pipeline_finished && !state.cancelling {
+                            cancel.cancel();
+                            state.start_cancel();
+                            continue;
+                        }
+                        // Finished, or asked twice: dropping the task
+                        // still kills any PTS process group. Wait for the
+                        // drop so the group is signalled before exiting.
+                        handle.abort();
+                        let _ = handle.await;
+                        // Exit with the step failure's code, not 0.
+                        return match state.error {
+                            Some(err) => Err(err),
+                            None if state.cancelling => Err(MjError::Cancelled),
+                            None => Ok(()),
+                        };
+                    }
 
+                    match key.code {
                         KeyCode::Up | KeyCode::Char('k') => state.scroll_up(),
                         KeyCode::Down | KeyCode::Char('j') => state.scroll_down(),
                         KeyCode::PageUp => state.scroll_page_up(),
@@ -283,10 +167,10 @@ async fn run_loop<B: ratatui::backend::Backend>(
                             state.scroll_half_page_down(),
 
                         KeyCode::Char('g') => {
-                            if let Some(TuiEvent::Key(KeyCode::Char('g'))) =
-                                poll_event(Duration::from_millis(50))
-                            {
-                                state.scroll_to_top();
+                            if let Some(TuiEvent::Key(next)) = poll_event(Duration::from_millis(50)) {
+                                if next.code == KeyCode::Char('g') {
+                                    state.scroll_to_top();
+                                }
                             }
                         }
                         KeyCode::Char('G') => state.scroll_to_bottom(),
@@ -304,4 +188,4 @@ async fn run_loop<B: ratatui::backend::Backend>(
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -9,20 +9,26 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::time::Duration;
 
 #[derive(Debug)]
 pub enum TuiEvent {
     Tick,
-    Key(KeyCode),
+    Key(KeyEvent),
+}
+
+/// Raw mode turns Ctrl-C into an ordinary key press.
+pub fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
 }
 
 pub fn poll_event(timeout: Duration) -> Option<TuiEvent> {
     if event::poll(timeout).ok()? {
         if let Ok(ev) = event::read() {
             match ev {
-                Event::Key(k) => Some(TuiEvent::Key(k.code)),
+                // Windows also reports releases.
+                Event::Key(k) if k.kind != KeyEventKind::Release => Some(TuiEvent::Key(k)),
                 _ => None,
             }
         } else {
@@ -11,18 +11,10 @@
 
 use std::time::{Instant, Duration};
 
+use crate::error::MjError;
 use crate::model::{DeviceSpecs, BenchResults};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PipelineStep {
-    Specs,
-    Pts,
-    Browser,
-    Csv,
-    Sheets,
-    Drive,
-    Done,
-}
+use crate::pipeline::{PipelineEvent, PipelineStep};
+use crate::pts_progress::PtsProgress;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActivePanel {
@@ -34,6 +26,11 @@ pub enum ActivePanel {
 pub struct TuiState {
     // Pipeline
     pub current_step: PipelineStep,
+    pub pipeline_finished: bool,
+    pub run_id: Option<String>,
+    /// The user asked to stop; waiting for the pipeline to wind down.
+    pub cancelling: bool,
+    pub cancelled_step: Option<PipelineStep>,
 
     // Logs
     pub logs: Vec<String>,
@@ -49,11 +46,15 @@ pub struct TuiState {
     // Progress bar animation
     pub progress_pos: u16,
     pub progress_dir: i8,
+    /// Real progress of the running PTS suite, when its output told us.
+    pub pts_progress: Option<PtsProgress>,
 
     // Timing
     pub step_start: Option<Instant>,
     pub step_elapsed: Duration,
     pub step_times: Vec<(PipelineStep, Duration)>,
+    pub skipped_steps: Vec<(PipelineStep, String)>,
+    pub restored_steps: Vec<PipelineStep>,
 
     // Success animation
     pub success_flash_ticks: u8,
@@ -62,6 +63,7 @@ pub struct TuiState {
     // Failure animation
     pub failed_step: Option<PipelineStep>,
     pub error_message: Option<String>,
+    pub error: Option<MjError>,
     pub failure_flash_ticks: u8,
 
     // Search
@@ -84,6 +86,10 @@ impl TuiState {
     pub fn new() -> Self {
         Self {
             current_step: PipelineStep::Specs,
+            pipeline_finished: false,
+            run_id: None,
+            cancelling: false,
+            cancelled_step: None,
 
             logs: Vec::new(),
             log_scroll: 0,
@@ -95,16 +101,20 @@ impl TuiState {
 
             progress_pos: 0,
             progress_dir: 1,
+            pts_progress: None,
 
             step_start: None,
             step_elapsed: Duration::ZERO,
             step_times: Vec::new(),
+            skipped_steps: Vec::new(),
+            restored_steps: Vec::new(),
 
             success_flash_ticks: 0,
             last_completed_step: None,
 
             failed_step: None,
             error_message: None,
+            error: None,
             failure_flash_ticks: 0,
 
             in_search_mode: false,
@@ -117,6 +127,82 @@ impl TuiState {
         }
     }
 
+    /* ───────────── Pipeline ───────────── */
+
+    pub fn set_step(&mut self, step: PipelineStep) {
+        self.current_step = step;
+    }
+
+    pub fn apply_event(&mut self, event: PipelineEvent) {
+        match event {
+            PipelineEvent::RunStarted { run_id } => {
+                self.run_id = Some(run_id.clone());
+                self.log(format!("Run ID: {}", run_id));
+            }
+            PipelineEvent::StepStarted(step) => {
+                self.set_step(step);
+                self.start_step_timer();
+                self.reset_progress_bar();
+                self.pts_progress = None;
+            }
+            PipelineEvent::StepRestored(step) => {
+                self.log(format!("Restored {} from checkpoint", step.label()));
+                self.restored_steps.push(step);
+            }
+            PipelineEvent::Log(msg) => self.log(msg),
+            PipelineEvent::SpecsCollected(specs) => self.specs = Some(*specs),
+            PipelineEvent::BenchUpdated(bench) => self.bench = Some(*bench),
+            PipelineEvent::PtsProgress(progress) => self.pts_progress = Some(progress),
+            PipelineEvent::StepCompleted(step, _) => {
+                self.stop_step_timer();
+                self.trigger_success(step);
+            }
+            PipelineEvent::StepSkipped(step, reason) => {
+                self.log(format!("Skipped {}: {}", step.label(), reason));
+                self.skipped_steps.push((step, reason));
+            }
+            PipelineEvent::StepFailed(step, err) => {
+                self.log(format!("ERROR: {}", err));
+                self.log(format!("hint: {}", err.hint()));
+                self.step_start = None;
+                self.trigger_failure(step, err.to_string());
+                self.error = Some(err);
+            }
+            PipelineEvent::StepCancelled(step) => {
+                self.step_start = None;
+                self.cancelled_step = Some(step);
+            }
+            PipelineEvent::Finished(summary) => {
+                if summary.succeeded() {
+                    self.log("Pipeline complete.");
+                } else if let Some(id) = &self.run_id {
+                    self.log(format!("Resume with: mj-benchmark resume {}", id));
+                }
+                self.set_step(PipelineStep::Done);
+                self.pipeline_finished = true;
+            }
+        }
+    }
+
+    /// Ask the pipeline to stop; the step is reported when it has.
+    pub fn start_cancel(&mut self) {
+        if !self.cancelling {
+            self.cancelling = true;
+            self.log("Cancelling… (press q again to quit without waiting)");
+        }
+    }
+
+    pub fn is_step_completed(&self, step: PipelineStep) -> bool {
+        self.step_times.iter().any(|(s, _)| *s == step) || self.restored_steps.contains(&step)
+    }
+
+    pub fn skip_reason(&self, step: PipelineStep) -> Option<&str> {
+        self.skipped_steps
+            .iter()
+            .find(|(s, _)| *s == step)
+            .map(|(_, reason)| reason.as_str())
+    }
+
     /* ───────────── Logging ───────────── */
 
     pub fn log<S: Into<String>>(&mut self, msg: S) {
@@ -18,7 +18,9 @@ use ratatui::{
     text::{Span, Spans},
 };
 
-use crate::tui::state::{TuiState, PipelineStep, ActivePanel};
+use crate::pipeline::PipelineStep;
+use crate::pts_progress::PtsProgress;
+use crate::tui::state::{TuiState, ActivePanel};
 use crate::tui::theme::*;
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, state: &TuiState) {
@@ -81,9 +83,9 @@ fn draw_sidebar<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
 
 fn draw_main<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
     let constraints = if state.in_search_mode {
-        vec![Constraint::Length(8), Constraint::Min(5), Constraint::Length(3)]
+        vec![Constraint::Length(10), Constraint::Min(5), Constraint::Length(3)]
     } else {
-        vec![Constraint::Length(8), Constraint::Min(5)]
+        vec![Constraint::Length(10), Constraint::Min(5)]
     };
 
     let chunks = Layout::default()
@@ -107,19 +109,21 @@ fn draw_progress<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
 
     let mut items: Vec<ListItem> = Vec::new();
 
-    for (label, step) in [
-        ("Specs", PipelineStep::Specs),
-        ("PTS Benchmarks", PipelineStep::Pts),
-        ("Browser Benchmarks", PipelineStep::Browser),
-        ("CSV Build", PipelineStep::Csv),
-        ("Google Sheets Sync", PipelineStep::Sheets),
-        ("Google Drive Upload", PipelineStep::Drive),
-    ] {
-        let is_active = step == state.current_step;
-        let is_done = step_completed(step, state.current_step);
+    for step in PipelineStep::ALL {
+        let label = step.label();
+        let is_active = step == state.current_step
+            && state.failed_step != Some(step)
+            && state.cancelled_step != Some(step);
+        let is_done = state.is_step_completed(step);
+
+        let skip_reason = state.skip_reason(step);
 
         let (marker, style) = if state.failed_step == Some(step) {
             ("✖", state.failure_style())
+        } else if state.cancelled_step == Some(step) {
+            ("⊘", text_style().add_modifier(Modifier::DIM))
+        } else if skip_reason.is_some() {
+            ("–", text_style().add_modifier(Modifier::DIM))
         } else if is_active {
             (state.spinner(), state.pulse_style())
         } else if state.is_flashing_success(step) {
@@ -130,10 +134,18 @@ fn draw_progress<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
             (" ", text_style())
         };
 
-        let time = if is_active {
+        let time = if state.cancelled_step == Some(step) {
+            "cancelled".to_string()
+        } else if is_active && state.cancelling {
+            format!("{} · cancelling…", state.formatted_elapsed_for_current())
+        } else if is_active {
             state.formatted_elapsed_for_current()
+        } else if state.restored_steps.contains(&step) {
+            "restored".to_string()
         } else if is_done {
             state.formatted_elapsed_for_step(step)
+        } else if let Some(reason) = skip_reason {
+            format!("skipped: {}", reason)
         } else {
             String::new()
         };
@@ -144,12 +156,16 @@ fn draw_progress<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
             Span::styled(time, style),
         ])));
 
-        if is_active {
-            let bar = render_progress_bar(state, 20);
-            items.push(ListItem::new(Spans::from(vec![
-                Span::styled("    ", text_style()),
-                Span::styled(bar, state.pulse_style()),
-            ])));
+        match (&state.pts_progress, is_active && step == PipelineStep::Pts) {
+            (Some(progress), true) => push_pts_progress(&mut items, state, progress),
+            _ if is_active => {
+                let bar = render_progress_bar(state, 20);
+                items.push(ListItem::new(Spans::from(vec![
+                    Span::styled("    ", text_style()),
+                    Span::styled(bar, state.pulse_style()),
+                ])));
+            }
+            _ => {}
         }
     }
 
@@ -215,21 +231,43 @@ fn draw_search_bar<B: Backend>(f: &mut Frame<B>, state: &TuiState, area: Rect) {
     f.render_widget(para, inner);
 }
 
+/// Percentage bar plus the running test and sub-test for the PTS step.
+fn push_pts_progress(items: &mut Vec<ListItem>, state: &TuiState, progress: &PtsProgress) {
+    let mut position = format!("test {}/{}", progress.test_index, progress.test_count);
+    if progress.trial > 0 {
+        match progress.trials {
+            Some(n) => position.push_str(&format!(" · run {}/{}", progress.trial, n)),
+            None => position.push_str(&format!(" · run {}", progress.trial)),
+        }
+    }
+
+    items.push(ListItem::new(Spans::from(vec![
+        Span::styled("    ", text_style()),
+        Span::styled(render_fraction_bar(progress.fraction(), 20), state.pulse_style()),
+        Span::styled(format!(" {:>3}%  ", progress.percent()), state.pulse_style()),
+        Span::styled(position, text_style()),
+    ])));
+
+    let name = if progress.title.is_empty() { &progress.test } else { &progress.title };
+    let current = match &progress.sub_test {
+        Some(sub) => format!("{} [{}]", name, sub),
+        None => name.clone(),
+    };
+    items.push(ListItem::new(Spans::from(vec![
+        Span::styled("    ", text_style()),
+        Span::styled(current, text_style().add_modifier(Modifier::DIM)),
+    ])));
+}
+
+fn render_fraction_bar(fraction: f64, width: u16) -> String {
+    let filled = (fraction * width as f64).round() as u16;
+    (0..width)
+        .map(|i| if i < filled { '█' } else { '░' })
+        .collect()
+}
+
 fn render_progress_bar(state: &TuiState, width: u16) -> String {
     (0..width)
         .map(|i| if i == state.progress_pos { '█' } else { '░' })
         .collect()
 }
-
-fn step_completed(step: PipelineStep, current: PipelineStep) -> bool {
-    use PipelineStep::*;
-    matches!(
-        (step, current),
-        (Specs, Pts | Browser | Csv | Sheets | Drive | Done)
-            | (Pts, Browser | Csv | Sheets | Drive | Done)
-            | (Browser, Csv | Sheets | Drive | Done)
-            | (Csv, Sheets | Drive | Done)
-            | (Sheets, Drive | Done)
-            | (Drive, Done)
-    )
-}
\ No newline at end of file
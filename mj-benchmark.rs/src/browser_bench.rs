@@ -9,117 +9,690 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use fantoccini::{Client, Locator};
+//! Browser suites driven over WebDriver. Each suite is described as data
+//! ([`BrowserSuite`]): its start page, how the run is started, when it
+//! counts as finished, and where the score and sub-scores are read. A
+//! running suite is polled up to a per-suite timeout. With
+//! `[[profiles.<name>.browsers]]` the suites run once per configured
+//! browser, each in its own WebDriver session.
+
+use fantoccini::error::CmdError;
+use fantoccini::{Client, ClientBuilder, Locator};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::time::Duration;
-use tokio::time::sleep;
+use thiserror::Error;
+use tokio::time::{sleep, Instant};
+
+use crate::model::{BenchResults, SuiteScore};
+use crate::webdriver::WebDriverMode;
+
+#[derive(Debug, Clone, Error)]
+pub enum BrowserError {
+    #[error("cannot connect to WebDriver at {0}: {1}")]
+    WebDriverUnavailable(String, String),
+    #[error("no {0} found on PATH")]
+    DriverNotFound(String),
+    #[error("could not start {0}: {1}")]
+    DriverStart(String, String),
+    #[error("browser suites in {0}: {1}")]
+    LocalSuites(String, String),
+    #[error("{suite}: {reason}")]
+    SuiteFailed { suite: String, reason: String },
+    #[error("{suite} did not finish within {limit_s}s{}", last_seen(.last_text))]
+    SuiteTimedOut {
+        suite: String,
+        limit_s: u64,
+        /// Score text at the last poll, if there was any.
+        last_text: Option<String>,
+    },
+}
+
+fn last_seen(text: &Option<String>) -> String {
+    match text {
+        Some(text) => format!(" (score element showed '{}')", text),
+        None => " (no score shown)".into(),
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BrowserBenchResults {
-    pub speedometer: Option<f64>,
-    pub jetstream: Option<f64>,
-    pub motionmark: Option<f64>,
+    /// One per suite, in run order; `version` is left for the caller.
+    pub suites: Vec<SuiteScore>,
+    pub browser_name: Option<String>,
+    pub browser_version: Option<String>,
+    /// "Blink", "Gecko" or "WebKit", from the browser name.
+    pub engine: Option<String>,
+    pub driver_version: Option<String>,
+    /// The first suite that ran out of time; the suites after it still ran.
+    pub timed_out: Option<BrowserError>,
+}
+
+/// One `[[profiles.<name>.browsers]]` entry: a browser to run the suites
+/// in and how to reach its driver.
+///
+/// ```toml
+/// [[profiles.lab.browsers]]
+/// name = "chromium"
+/// webdriver = "chromedriver"
+/// capabilities = { "goog:chromeOptions" = { binary = "/usr/bin/chromium" } }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrowserTarget {
+    /// Tags this browser's results and CSV columns.
+    pub name: String,
+    /// The profile's `webdriver` if unset.
+    pub webdriver: Option<WebDriverMode>,
+    /// The profile's `webdriver_url` if unset; only used with
+    /// `webdriver = "external"`.
+    pub webdriver_url: Option<String>,
+    /// W3C capabilities for the new session, e.g. `browserName`.
+    #[serde(default)]
+    pub capabilities: Map<String, Value>,
+}
+
+impl BrowserTarget {
+    /// Name of the single target used when a profile lists no browsers.
+    pub const DEFAULT_NAME: &'static str = "default";
+
+    /// Whatever the profile's `webdriver`/`webdriver_url` point at, with
+    /// the driver's default capabilities.
+    pub fn profile_default() -> Self {
+        Self {
+            name: Self::DEFAULT_NAME.into(),
+            webdriver: None,
+            webdriver_url: None,
+            capabilities: Map::new(),
+        }
+    }
+}
+
+pub const DEFAULT_WEBDRIVER_URL: &str = "http://localhost:9515";
+
+/// Where the suites are opened unless `browser_suites` serves a local copy.
+pub const BROWSERBENCH_URL: &str = "https://browserbench.org/";
+
+/// Per-suite limit when the profile sets no `timeouts.browser_suite`.
+pub const DEFAULT_SUITE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often a running suite's page is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A browser suite: where it lives, how it is started, when it is done
+/// and where its results are read. Built-ins come from
+/// [`builtin_suites`]; `[[profiles.<name>.browser_suite_defs]]` adds
+/// more or replaces one by `key`, except those with a fixed CSV column.
+///
+/// ```toml
+/// [[profiles.lab.browser_suite_defs]]
+/// key = "jetstream3"
+/// name = "JetStream 3.0"
+/// version = "3.0"
+/// path = "JetStream3.0/"
+/// start = { click = "#start-button" }
+/// score = { css = "#result-number" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrowserSuite {
+    /// Name in `browser_tests`; the metric key is `<key>_score`.
+    pub key: String,
+    /// Column label prefix, e.g. "Speedometer 3.1".
+    pub name: String,
+    /// Version the name refers to; a local copy may pin a finer one.
+    pub version: String,
+    /// Start page relative to browserbench.org or the local copy (its
+    /// first segment is the suite's directory), or an absolute URL.
+    pub path: String,
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    pub start: SuiteStart,
+    #[serde(default)]
+    pub done: SuiteDone,
+    pub score: Extract,
+    /// Script returning an object of sub-score name to number, run once
+    /// the suite is done.
+    pub subscores: Option<String>,
+}
+
+fn default_unit() -> String {
+    "score".into()
+}
+
+/// How a suite run is started once its page has loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuiteStart {
+    /// Click the element matching this CSS selector.
+    Click(String),
+    /// Run this script.
+    Script(String),
+    /// The page starts itself, e.g. from a URL parameter in `path`.
+    Load,
+}
+
+/// When a running suite counts as finished.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuiteDone {
+    /// As soon as `score` reads a number.
+    #[default]
+    Score,
+    /// Once the element matching this CSS selector is visible.
+    Visible(String),
+    /// Once this script returns a truthy value.
+    Script(String),
+}
+
+/// Where a value is read from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extract {
+    /// Text of the element matching this CSS selector, while visible.
+    /// Before the run ends it is usually missing, hidden or empty.
+    Css(String),
+    /// Return value of this script: a number, or text starting with one.
+    Script(String),
+}
+
+impl BrowserSuite {
+    /// The suite's start page under `base`, e.g. [`BROWSERBENCH_URL`].
+    pub fn url(&self, base: &str) -> String {
+        if self.is_absolute() {
+            return self.path.clone();
+        }
+        format!("{}/{}", base.trim_end_matches('/'), self.path)
+    }
+
+    /// True when `path` is a full URL, which a local copy cannot serve.
+    pub fn is_absolute(&self) -> bool {
+        self.path.contains("://")
+    }
+
+    /// First segment of a relative `path`: the suite's directory.
+    pub fn dir(&self) -> Option<&str> {
+        if self.is_absolute() {
+            return None;
+        }
+        self.path.split(['/', '?', '#']).next().filter(|d| !d.is_empty())
+    }
+
+    /// One line for `--dry-run`.
+    pub fn describe(&self) -> String {
+        let start = match &self.start {
+            SuiteStart::Click(selector) => format!("click '{}'", selector),
+            SuiteStart::Script(_) => "run the start script".into(),
+            SuiteStart::Load => "starts on load".into(),
+        };
+        let done = match &self.done {
+            SuiteDone::Score => "the score shows a number".to_string(),
+            SuiteDone::Visible(selector) => format!("'{}' is visible", selector),
+            SuiteDone::Script(_) => "the done script returns true".into(),
+        };
+        let score = match &self.score {
+            Extract::Css(selector) => format!("'{}'", selector),
+            Extract::Script(_) => "a script".into(),
+        };
+        let subscores = if self.subscores.is_some() { ", plus sub-scores" } else { "" };
+        format!("{}; done when {}; score from {}{}", start, done, score, subscores)
+    }
+}
+
+/// Sub-scores of Speedometer 3: the mean of each top-level test, in ms.
+///
+/// The page has no public API for them, so this reads
+/// `window.benchmarkClient._metrics`, a private field of Speedometer's
+/// runner that a later revision may rename or drop. Anything missing or
+/// shaped differently gives no sub-scores; the score itself is read from
+/// the page as for every other suite.
+const SPEEDOMETER3_SUBSCORES_JS: &str = r#"
+const client = window.benchmarkClient;
+const metrics = client && client._metrics;
+if (!metrics || typeof metrics !== "object") return {};
+const out = {};
+for (const [name, metric] of Object.entries(metrics)) {
+  if (name.includes("/") || name.startsWith("Iteration-") || name === "Score" || name === "Geomean") continue;
+  if (metric && typeof metric.mean === "number") out[name] = metric.mean;
+}
+return out;
+"#;
+
+/// The suites mj-benchmark knows without configuration.
+pub fn builtin_suites() -> Vec<BrowserSuite> {
+    let clicked = |key: &str, name: &str, version: &str, start: &str, score: &str| BrowserSuite {
+        key: key.into(),
+        name: name.into(),
+        version: version.into(),
+        path: format!("{}/", name.replace(' ', "")),
+        unit: default_unit(),
+        start: SuiteStart::Click(start.into()),
+        done: SuiteDone::Score,
+        score: Extract::Css(score.into()),
+        subscores: None,
+    };
+    vec![
+        BrowserSuite {
+            unit: "runs/min".into(),
+            ..clicked("speedometer", "Speedometer 2.1", "2.1", ".run-button", ".result-number")
+        },
+        clicked("jetstream", "JetStream 2.2", "2.2", "#start-button", "#result-number"),
+        clicked("motionmark", "MotionMark 1.3", "1.3", ".start-button", ".result-number"),
+        BrowserSuite {
+            unit: "runs/min".into(),
+            subscores: Some(SPEEDOMETER3_SUBSCORES_JS.into()),
+            ..clicked("speedometer3", "Speedometer 3.1", "3.1", ".start-tests-button", "#result-number")
+        },
+    ]
+}
+
+/// The suites to run, in order: the `tests` keys if given, else the
+/// suites with fixed CSV columns ([`BenchResults::FIXED_SUITE_KEYS`]) then
+/// every suite in `defs`. A `defs` entry replaces the built-in with the
+/// same key; those with fixed columns cannot be replaced, since the column
+/// names their version (e.g. "JetStream 2.2 Score").
+pub fn select_suites(tests: Option<&[String]>, defs: &[BrowserSuite]) -> Result<Vec<BrowserSuite>, String> {
+    let mut known = builtin_suites();
+    for (i, def) in defs.iter().enumerate() {
+        let valid = !def.key.is_empty()
+            && def
+                .key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(format!(
+                "invalid browser suite key '{}': use lowercase letters, digits and '_'",
+                def.key
+            ));
+        }
+        if defs[..i].iter().any(|d| d.key == def.key) {
+            return Err(format!("browser_suite_defs defines '{}' twice", def.key));
+        }
+        if BenchResults::FIXED_SUITE_KEYS.contains(&def.key.as_str()) {
+            let fixed = known.iter().find(|s| s.key == def.key).map_or("", |s| s.name.as_str());
+            return Err(format!(
+                "browser_suite_defs cannot replace '{}': it fills the \"{} Score\" column; give '{}' its own key",
+                def.key, fixed, def.name
+            ));
+        }
+        match known.iter_mut().find(|s| s.key == def.key) {
+            Some(builtin) => *builtin = def.clone(),
+            None => known.push(def.clone()),
+        }
+    }
+
+    let keys: Vec<&str> = match tests {
+        Some(tests) => tests.iter().map(String::as_str).collect(),
+        None => BenchResults::FIXED_SUITE_KEYS
+            .into_iter()
+            .chain(defs.iter().map(|d| d.key.as_str()))
+            .fold(Vec::new(), |mut keys, key| {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+                keys
+            }),
+    };
+
+    let mut suites: Vec<BrowserSuite> = Vec::new();
+    for key in keys {
+        if suites.iter().any(|s| s.key == key) {
+            return Err(format!("browser_tests lists '{}' twice", key));
+        }
+        let suite = known.iter().find(|s| s.key == key).ok_or_else(|| {
+            let names: Vec<&str> = known.iter().map(|s| s.key.as_str()).collect();
+            format!("unknown browser test '{}' (known: {})", key, names.join(", "))
+        })?;
+        suites.push(suite.clone());
+    }
+    Ok(suites)
 }
 
-pub async fn run_browser_benchmarks() -> BrowserBenchResults {
+/// Text of the first visible element matching `arguments[0]`, or null.
+const SCORE_TEXT_JS: &str = r#"
+const el = document.querySelector(arguments[0]);
+if (!el || el.offsetParent === null) return null;
+const text = el.textContent.trim();
+return text === "" ? null : text;
+"#;
+
+/// Whether an element matching `arguments[0]` is visible.
+const VISIBLE_JS: &str = r#"
+const el = document.querySelector(arguments[0]);
+return !!el && el.offsetParent !== null;
+"#;
+
+/// Run `suites`, opened under `suites_url`, in one session opened with
+/// `capabilities`.
+pub async fn run_browser_benchmarks(
+    webdriver_url: &str,
+    capabilities: &Map<String, Value>,
+    suites: &[BrowserSuite],
+    suites_url: &str,
+    suite_timeout: Duration,
+    mut on_line: impl FnMut(&str),
+) -> Result<BrowserBenchResults, BrowserError> {
     let mut results = BrowserBenchResults {
-        speedometer: None,
-        jetstream: None,
-        motionmark: None,
+        suites: Vec::new(),
+        browser_name: None,
+        browser_version: None,
+        engine: None,
+        driver_version: None,
+        timed_out: None,
     };
 
+    results.driver_version = driver_version(webdriver_url).await;
+
     // Connect to WebDriver (ChromeDriver, GeckoDriver, etc.)
-    let client = Client::new("http://localhost:9515")
+    let mut builder = ClientBuilder::native();
+    builder.capabilities(capabilities.clone());
+    let client = builder
+        .connect(webdriver_url)
         .await
-        .expect("Failed to connect to WebDriver");
+        .map_err(|e| BrowserError::WebDriverUnavailable(webdriver_url.into(), e.to_string()))?;
 
-    // Speedometer
-    results.speedometer = run_speedometer(&client).await;
-
-    // JetStream
-    results.jetstream = run_jetstream(&client).await;
-
-    // MotionMark
-    results.motionmark = run_motionmark(&client).await;
+    if let Some((name, version)) = browser_version(&client).await {
+        results.engine = engine(&name).map(str::to_string);
+        results.browser_name = Some(name);
+        results.browser_version = Some(version);
+    }
 
+    (results.suites, results.timed_out) =
+        run_suites(&client, suites, suites_url, suite_timeout, &mut on_line).await;
     client.close().await.ok();
 
-    results
+    Ok(results)
 }
 
-async fn run_speedometer(c: &Client) -> Option<f64> {
-    println!("Running Speedometer 2.1...");
-    c.goto("https://browserbench.org/Speedometer2.1/").await.ok()?;
+/// One record per suite; a suite that fails is recorded without a score
+/// and the next one still runs. Also returns the first timeout, which
+/// the caller reports once the other suites are done.
+async fn run_suites(
+    c: &Client,
+    suites: &[BrowserSuite],
+    base: &str,
+    timeout: Duration,
+    on_line: &mut impl FnMut(&str),
+) -> (Vec<SuiteScore>, Option<BrowserError>) {
+    let mut scores = Vec::new();
+    let mut timed_out = None;
+    for suite in suites {
+        let mut record = SuiteScore {
+            key: suite.key.clone(),
+            name: suite.name.clone(),
+            unit: suite.unit.clone(),
+            ..SuiteScore::default()
+        };
+        match run_suite(c, suite, base, timeout, on_line).await {
+            Ok((score, subscores)) => {
+                on_line(&format!("{}: {}", suite.name, score));
+                record.score = Some(score);
+                record.subscores = subscores;
+            }
+            Err(e) => {
+                on_line(&format!("{} failed: {}", suite.name, e));
+                record.error = Some(e.to_string());
+                if matches!(e, BrowserError::SuiteTimedOut { .. }) {
+                    timed_out.get_or_insert(e);
+                }
+            }
+        }
+        scores.push(record);
+    }
+    (scores, timed_out)
+}
 
-    // Click "Start Test"
-    if c.find(Locator::Css(".run-button")).await.is_ok() {
-        c.find(Locator::Css(".run-button"))
+async fn run_suite(
+    c: &Client,
+    suite: &BrowserSuite,
+    base: &str,
+    timeout: Duration,
+    on_line: &mut impl FnMut(&str),
+) -> Result<(f64, BTreeMap<String, f64>), BrowserError> {
+    let failed = |reason: String| BrowserError::SuiteFailed {
+        suite: suite.name.clone(),
+        reason,
+    };
+
+    let url = suite.url(base);
+    on_line(&format!("Running {}...", suite.name));
+    c.goto(&url)
+        .await
+        .map_err(|e| failed(format!("cannot open {}: {}", url, e)))?;
+    match &suite.start {
+        SuiteStart::Click(selector) => c
+            .find(Locator::Css(selector))
             .await
-            .ok()?
+            .map_err(|e| failed(format!("no start button '{}': {}", selector, e)))?
             .click()
             .await
-            .ok()?;
+            .map_err(|e| failed(format!("cannot click '{}': {}", selector, e)))?,
+        SuiteStart::Script(script) => {
+            c.execute(script, vec![])
+                .await
+                .map_err(|e| failed(format!("start script failed: {}", e)))?;
+        }
+        SuiteStart::Load => {}
     }
 
-    // Wait for test to finish (Speedometer takes ~1–2 minutes)
-    sleep(Duration::from_secs(90)).await;
+    let started = Instant::now();
+    let mut last_report = started;
+    loop {
+        sleep(POLL_INTERVAL).await;
+
+        let read_failed = |e: CmdError| failed(format!("cannot read the page: {}", e));
+        let last_text = read_score(c, &suite.score).await.map_err(read_failed)?;
+        let score = last_text.as_deref().and_then(parse_score);
+        let done = match &suite.done {
+            SuiteDone::Score => score.is_some(),
+            SuiteDone::Visible(selector) => {
+                let visible = c.execute(VISIBLE_JS, vec![selector.as_str().into()]).await;
+                is_truthy(&visible.map_err(read_failed)?)
+            }
+            SuiteDone::Script(script) => is_truthy(&c.execute(script, vec![]).await.map_err(read_failed)?),
+        };
 
-    // Extract score
-    if let Ok(el) = c.find(Locator::Css(".result-number")).await {
-        let text = el.text().await.ok()?;
-        return text.trim().parse::<f64>().ok();
+        if done {
+            let score = score.ok_or_else(|| {
+                failed(format!(
+                    "finished, but the score reads '{}'",
+                    last_text.as_deref().unwrap_or("")
+                ))
+            })?;
+            let subscores = match &suite.subscores {
+                Some(script) => read_subscores(c, script).await.unwrap_or_else(|e| {
+                    on_line(&format!("{}: no sub-scores ({})", suite.name, e));
+                    BTreeMap::new()
+                }),
+                None => BTreeMap::new(),
+            };
+            return Ok((score, subscores));
+        }
+
+        if started.elapsed() >= timeout {
+            return Err(BrowserError::SuiteTimedOut {
+                suite: suite.name.clone(),
+                limit_s: timeout.as_secs(),
+                last_text,
+            });
+        }
+        if last_report.elapsed() >= Duration::from_secs(30) {
+            on_line(&format!("{}: still running ({}s)", suite.name, started.elapsed().as_secs()));
+            last_report = Instant::now();
+        }
     }
+}
 
-    None
+/// The score's text as shown, or `None` while there is none.
+async fn read_score(c: &Client, from: &Extract) -> Result<Option<String>, CmdError> {
+    let value = match from {
+        Extract::Css(selector) => c.execute(SCORE_TEXT_JS, vec![selector.as_str().into()]).await?,
+        Extract::Script(script) => c.execute(script, vec![]).await?,
+    };
+    Ok(match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    })
 }
 
-async fn run_jetstream(c: &Client) -> Option<f64> {
-    println!("Running JetStream 2.2...");
-    c.goto("https://browserbench.org/JetStream2.2/").await.ok()?;
+async fn read_subscores(c: &Client, script: &str) -> Result<BTreeMap<String, f64>, String> {
+    let value = c.execute(script, vec![]).await.map_err(|e| e.to_string())?;
+    let Value::Object(entries) = value else {
+        return Err("the script returned no object".into());
+    };
+    Ok(entries
+        .into_iter()
+        .filter_map(|(name, v)| {
+            let n = v.as_f64().or_else(|| v.as_str().and_then(parse_score))?;
+            Some((name, n))
+        })
+        .collect())
+}
 
-    // Click "Start"
-    if c.find(Locator::Css("#start-button")).await.is_ok() {
-        c.find(Locator::Css("#start-button"))
-            .await
-            .ok()?
-            .click()
-            .await
-            .ok()?;
+/// JavaScript truthiness of a script's return value.
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
     }
+}
 
-    // JetStream takes ~1–2 minutes
-    sleep(Duration::from_secs(90)).await;
+/// The number at the start of a score display, e.g. "142" or
+/// "1,024.53 ± 0.61%".
+fn parse_score(text: &str) -> Option<f64> {
+    let number = text.split_whitespace().next()?.replace(',', "");
+    number.parse::<f64>().ok().filter(|n| n.is_finite())
+}
 
-    // Extract score
-    if let Ok(el) = c.find(Locator::Css("#result-number")).await {
-        let text = el.text().await.ok()?;
-        return text.trim().parse::<f64>().ok();
-    }
+/// chromedriver reports its build in `/status`; geckodriver does not.
+async fn driver_version(webdriver_url: &str) -> Option<String> {
+    let url = format!("{}/status", webdriver_url.trim_end_matches('/'));
+    let status: serde_json::Value = reqwest::get(url).await.ok()?.json().await.ok()?;
+    status["value"]["build"]["version"].as_str().map(str::to_string)
+}
 
+/// Browser name and version, parsed from the user agent.
+async fn browser_version(c: &Client) -> Option<(String, String)> {
+    let ua = c.execute("return navigator.userAgent", vec![]).await.ok()?;
+    parse_user_agent(ua.as_str()?)
+}
+
+fn parse_user_agent(ua: &str) -> Option<(String, String)> {
+    // Order matters: Edge and Chrome UAs also mention "Safari".
+    for (token, name) in [
+        ("Firefox/", "Firefox"),
+        ("Edg/", "Edge"),
+        ("Chrome/", "Chrome"),
+        ("Version/", "Safari"),
+    ] {
+        if let Some(pos) = ua.find(token) {
+            let version = ua[pos + token.len()..].split_whitespace().next()?;
+            return Some((name.into(), version.into()));
+        }
+    }
     None
 }
 
-async fn run_motionmark(c: &Client) -> Option<f64> {
-    println!("Running MotionMark 1.3...");
-    c.goto("https://browserbench.org/MotionMark1.3/").await.ok()?;
+/// Rendering engine behind a browser name from `parse_user_agent`.
+fn engine(browser: &str) -> Option<&'static str> {
+    match browser {
+        "Chrome" | "Edge" => Some("Blink"),
+        "Firefox" => Some("Gecko"),
+        "Safari" => Some("WebKit"),
+        _ => None,
+    }
+}
 
-    // Click "Start Test"
-    if c.find(Locator::Css(".start-button")).await.is_ok() {
-        c.find(Locator::Css(".start-button"))
-            .await
-            .ok()?
-            .click()
-            .await
-            .ok()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_is_the_leading_number() {
+        assert_eq!(parse_score("142"), Some(142.0));
+        assert_eq!(parse_score("1,024.53 ± 0.61%"), Some(1024.53));
+        assert_eq!(parse_score("-"), None);
+        assert_eq!(parse_score("Running..."), None);
     }
 
-    // MotionMark takes ~1 minute
-    sleep(Duration::from_secs(60)).await;
+    #[test]
+    fn user_agent_gives_browser_and_engine() {
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+        let chromium = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.6478.126 Safari/537.36";
+        assert_eq!(parse_user_agent(firefox), Some(("Firefox".into(), "128.0".into())));
+        assert_eq!(parse_user_agent(chromium), Some(("Chrome".into(), "126.0.6478.126".into())));
+        assert_eq!(engine("Firefox"), Some("Gecko"));
+        assert_eq!(engine("Chrome"), Some("Blink"));
+    }
+
+    #[test]
+    fn defs_replace_builtins_and_run_after_the_defaults() {
+        let defs = [BrowserSuite {
+            key: "jetstream3".into(),
+            name: "JetStream 3.0".into(),
+            version: "3.0".into(),
+            path: "JetStream3.0/".into(),
+            unit: "score".into(),
+            start: SuiteStart::Script("JetStream.start()".into()),
+            done: SuiteDone::Score,
+            score: Extract::Css("#result-number".into()),
+            subscores: None,
+        }];
+        let keys = |suites: Vec<BrowserSuite>| suites.into_iter().map(|s| s.key).collect::<Vec<_>>();
+
+        assert_eq!(keys(select_suites(None, &[]).unwrap()), BenchResults::FIXED_SUITE_KEYS);
+        assert_eq!(
+            keys(select_suites(None, &defs).unwrap()),
+            ["speedometer", "jetstream", "motionmark", "jetstream3"]
+        );
+        let tests = ["speedometer3".to_string(), "jetstream3".to_string()];
+        let picked = select_suites(Some(&tests), &defs).unwrap();
+        assert_eq!(picked[0].name, "Speedometer 3.1");
+        assert_eq!(picked[0].url(BROWSERBENCH_URL), "https://browserbench.org/Speedometer3.1/");
+        assert_eq!(picked[1].dir(), Some("JetStream3.0"));
 
-    // Extract score
-    if let Ok(el) = c.find(Locator::Css(".result-number")).await {
-        let text = el.text().await.ok()?;
-        return text.trim().parse::<f64>().ok();
+        let [jetstream3] = defs;
+        let speedometer3 = BrowserSuite { key: "speedometer3".into(), ..jetstream3.clone() };
+        let replaced = select_suites(Some(&["speedometer3".into()]), &[speedometer3]).unwrap();
+        assert_eq!(replaced[0].path, "JetStream3.0/");
+        let renamed = BrowserSuite { key: "jetstream".into(), ..jetstream3 };
+        let err = select_suites(None, &[renamed]).unwrap_err();
+        assert!(err.contains("fills the \"JetStream 2.2 Score\" column"), "{}", err);
+        assert!(select_suites(Some(&["octane".into()]), &[]).is_err());
     }
 
-    None
-}
\ No newline at end of file
+    #[test]
+    fn descriptors_read_from_toml() {
+        let suite: BrowserSuite = toml::from_str(
+            r##"
+            key = "speedometer3"
+            name = "Speedometer 3.1"
+            version = "3.1"
+            path = "Speedometer3.1/?startAutomatically=true"
+            unit = "runs/min"
+            start = "load"
+            done = { visible = "#summary" }
+            score = { css = "#result-number" }
+            "##,
+        )
+        .unwrap();
+        assert_eq!(suite.start, SuiteStart::Load);
+        assert_eq!(suite.done, SuiteDone::Visible("#summary".into()));
+        assert_eq!(suite.dir(), Some("Speedometer3.1"));
+    }
+
+    #[test]
+    fn timeout_names_what_was_on_screen() {
+        let err = BrowserError::SuiteTimedOut {
+            suite: "JetStream 2.2".into(),
+            limit_s: 600,
+            last_text: None,
+        };
+        assert_eq!(err.to_string(), "JetStream 2.2 did not finish within 600s (no score shown)");
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Per-run checkpoints under `~/.mj_bench/runs/<run-id>/`.
+//!
+//! The pipeline rewrites `checkpoint.json` after every finished step, so
+//! `mj-benchmark resume <run-id>` can continue at the first unfinished
+//! step instead of re-running PTS. `client_secret` is not saved; resume
+//! takes it from the run's profile or `--client-secret` again.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::metadata::RunMetadata;
+use crate::model::{BenchResults, DeviceSpecs};
+use crate::pipeline::{PipelineConfig, PipelineStep};
+
+#[derive(Debug, Clone, Error)]
+pub enum CheckpointError {
+    #[error("no checkpoint for run '{0}'")]
+    NotFound(String),
+    #[error("'{0}' is not a run ID")]
+    InvalidRunId(String),
+    #[error("IO error: {0}")]
+    Io(String),
+    #[error("corrupt checkpoint: {0}")]
+    Corrupt(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub metadata: RunMetadata,
+    pub config: PipelineConfig,
+    pub completed: Vec<PipelineStep>,
+    pub specs: Option<DeviceSpecs>,
+    pub bench: Option<BenchResults>,
+    pub row: Option<String>,
+}
+
+impl Checkpoint {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self {
+            metadata: RunMetadata::new(&config.timezone),
+            config,
+            completed: Vec::new(),
+            specs: None,
+            bench: None,
+            row: None,
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.metadata.run_id
+    }
+
+    pub fn is_completed(&self, step: PipelineStep) -> bool {
+        self.completed.contains(&step)
+    }
+
+    pub fn save(&self) -> Result<(), CheckpointError> {
+        let dir = run_dir(self.run_id());
+        fs::create_dir_all(&dir).map_err(|e| CheckpointError::Io(e.to_string()))?;
+
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| CheckpointError::Corrupt(e.to_string()))?;
+
+        // Write then rename so a crash mid-write never leaves a torn file.
+        let tmp = dir.join("checkpoint.json.tmp");
+        write_private(&tmp, data.as_bytes()).map_err(|e| CheckpointError::Io(e.to_string()))?;
+        fs::rename(&tmp, dir.join("checkpoint.json"))
+            .map_err(|e| CheckpointError::Io(e.to_string()))
+    }
+
+    pub fn load(run_id: &str) -> Result<Self, CheckpointError> {
+        // Run IDs are UUIDs; anything else could name a path outside runs/.
+        if Uuid::parse_str(run_id).is_err() {
+            return Err(CheckpointError::InvalidRunId(run_id.into()));
+        }
+        let path = run_dir(run_id).join("checkpoint.json");
+        let data = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => CheckpointError::NotFound(run_id.into()),
+            _ => CheckpointError::Io(e.to_string()),
+        })?;
+        serde_json::from_str(&data).map_err(|e| CheckpointError::Corrupt(e.to_string()))
+    }
+}
+
+pub fn runs_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(format!("{}/.mj_bench/runs", home))
+}
+
+pub fn run_dir(run_id: &str) -> PathBuf {
+    runs_dir().join(run_id)
+}
+
+/// Replace `path` with `data`, readable by the owner only: the checkpoint
+/// holds the run's config.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let _ = fs::remove_file(path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_uuids_are_run_ids() {
+        for bad in ["../../.ssh", "..", "a/b", ""] {
+            assert!(matches!(
+                Checkpoint::load(bad),
+                Err(CheckpointError::InvalidRunId(_))
+            ));
+        }
+    }
+}
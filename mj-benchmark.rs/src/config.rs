@@ -0,0 +1,400 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! `~/.mj_bench/config.toml` with named run profiles.
+//!
+//! ```toml
+//! default_profile = "lab"
+//!
+//! [profiles.lab]
+//! sheet_id = "1AbC…"
+//! drive_folder_id = "0XyZ…"
+//! csv_path = "/srv/bench/lab.csv"
+//! mode = "cli"
+//! pts_suite = "lab-core"
+//! pts_tests = ["pts/compress-7zip", "pts/openssl", "pts/x264"]
+//! webdriver_url = "http://localhost:4444"
+//! webdriver = "external"  # "auto" starts chromedriver/geckodriver instead
+//! browser_suites = "/srv/bench/browser-suites"  # pinned local copy
+//! browser_tests = ["speedometer3", "jetstream", "motionmark", "jetstream3"]
+//!
+//! [[profiles.lab.pts_columns]]
+//! column = "x264 Bosphorus 4K"
+//! test = "pts/x264"
+//! description = "Video Input: Bosphorus 4K"
+//! scale = "Frames Per Second"
+//! unit = "fps"
+//!
+//! # run the browser suites in each; without entries, once against
+//! # webdriver_url
+//! [[profiles.lab.browsers]]
+//! name = "chromium"
+//! webdriver = "chromedriver"
+//! capabilities = { "goog:chromeOptions" = { binary = "/usr/bin/chromium" } }
+//!
+//! [[profiles.lab.browsers]]
+//! name = "firefox"
+//! webdriver = "geckodriver"
+//! capabilities = { browserName = "firefox" }
+//!
+//! # a suite mj-benchmark does not know; see browser_bench::BrowserSuite
+//! [[profiles.lab.browser_suite_defs]]
+//! key = "jetstream3"
+//! name = "JetStream 3.0"
+//! version = "3.0"
+//! path = "JetStream3.0/"
+//! start = { click = "#start-button" }
+//! score = { css = "#result-number" }
+//!
+//! # seconds; omitted steps have no limit
+//! [profiles.lab.timeouts]
+//! pts = 14400
+//! pts_test = 3600
+//! browser = 900
+//! ```
+//!
+//! Precedence: CLI flag > selected profile > built-in default.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::browser_bench::{self, BrowserSuite, BrowserTarget};
+use crate::metadata;
+use crate::pipeline::{PipelineConfig, PipelineStep, Timeouts};
+use crate::pts;
+use crate::pts_results::PtsColumn;
+use crate::webdriver::WebDriverMode;
+
+pub const DEFAULT_CSV_PATH: &str = "mj_benchmarks.csv";
+pub const DEFAULT_MODE: &str = "tui";
+
+#[derive(Debug, Clone, Error)]
+pub enum ConfigError {
+    #[error("IO error reading {0}: {1}")]
+    Io(String, String),
+    #[error("invalid config {0}: {1}")]
+    Parse(String, String),
+    #[error("unknown profile '{0}'")]
+    UnknownProfile(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Every field is optional so a profile (or the CLI) only overrides what
+/// it sets.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub sheet_id: Option<String>,
+    pub drive_folder_id: Option<String>,
+    pub csv_path: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub local_only: Option<bool>,
+    pub mode: Option<String>,
+    pub pts_suite: Option<String>,
+    pub pts_tests: Option<Vec<String>>,
+    pub pts_columns: Option<Vec<PtsColumn>>,
+    pub native_fallback: Option<bool>,
+    pub pts_batch_setup: Option<bool>,
+    pub webdriver_url: Option<String>,
+    pub webdriver: Option<WebDriverMode>,
+    pub browsers: Option<Vec<BrowserTarget>>,
+    pub browser_suites: Option<String>,
+    pub browser_tests: Option<Vec<String>>,
+    pub browser_suite_defs: Option<Vec<BrowserSuite>>,
+    pub csv_stats: Option<bool>,
+    pub only: Option<Vec<PipelineStep>>,
+    pub skip: Option<Vec<PipelineStep>>,
+    pub output_json: Option<String>,
+    pub timezone: Option<String>,
+    pub timeouts: Option<Timeouts>,
+}
+
+impl Profile {
+    /// Layer `over` on top of `self`; values set in `over` win.
+    pub fn merge(self, over: Profile) -> Profile {
+        Profile {
+            sheet_id: over.sheet_id.or(self.sheet_id),
+            drive_folder_id: over.drive_folder_id.or(self.drive_folder_id),
+            csv_path: over.csv_path.or(self.csv_path),
+            client_id: over.client_id.or(self.client_id),
+            client_secret: over.client_secret.or(self.client_secret),
+            local_only: over.local_only.or(self.local_only),
+            mode: over.mode.or(self.mode),
+            pts_suite: over.pts_suite.or(self.pts_suite),
+            pts_tests: over.pts_tests.or(self.pts_tests),
+            pts_columns: over.pts_columns.or(self.pts_columns),
+            native_fallback: over.native_fallback.or(self.native_fallback),
+            pts_batch_setup: over.pts_batch_setup.or(self.pts_batch_setup),
+            webdriver_url: over.webdriver_url.or(self.webdriver_url),
+            webdriver: over.webdriver.or(self.webdriver),
+            browsers: over.browsers.or(self.browsers),
+            browser_suites: over.browser_suites.or(self.browser_suites),
+            browser_tests: over.browser_tests.or(self.browser_tests),
+            browser_suite_defs: over.browser_suite_defs.or(self.browser_suite_defs),
+            csv_stats: over.csv_stats.or(self.csv_stats),
+            only: over.only.or(self.only),
+            skip: over.skip.or(self.skip),
+            output_json: over.output_json.or(self.output_json),
+            timezone: over.timezone.or(self.timezone),
+            timeouts: match (self.timeouts, over.timeouts) {
+                (Some(base), Some(over)) => Some(base.merge(over)),
+                (base, over) => over.or(base),
+            },
+        }
+    }
+}
+
+/// Effective settings for one run after merging config and CLI.
+#[derive(Debug, Clone)]
+pub struct RunSettings {
+    pub config_path: PathBuf,
+    pub profile: Option<String>,
+    pub mode: String,
+    pub pipeline: PipelineConfig,
+}
+
+pub fn default_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(format!("{}/.mj_bench/config.toml", home))
+}
+
+pub fn load(path: &Path) -> Result<ConfigFile, ConfigError> {
+    let data = match fs::read_to_string(path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+        Err(e) => return Err(ConfigError::Io(path.display().to_string(), e.to_string())),
+    };
+
+    toml::from_str(&data).map_err(|e| ConfigError::Parse(path.display().to_string(), e.to_string()))
+}
+
+/// Resolve the effective settings for `profile` (or the file's
+/// `default_profile`) with `cli` layered on top.
+pub fn resolve(
+    path: Option<&Path>,
+    profile: Option<&str>,
+    cli: Profile,
+) -> Result<RunSettings, ConfigError> {
+    let config_path = path.map(Path::to_path_buf).unwrap_or_else(default_path);
+    let mut file = load(&config_path)?;
+
+    let name = profile.map(str::to_string).or(file.default_profile.take());
+    let base = match &name {
+        Some(n) => file
+            .profiles
+            .remove(n)
+            .ok_or_else(|| ConfigError::UnknownProfile(n.clone()))?,
+        None => Profile::default(),
+    };
+
+    let merged = base.merge(cli);
+
+    let timezone = merged.timezone.unwrap_or_else(|| metadata::DEFAULT_TIMEZONE.into());
+    metadata::parse_timezone(&timezone).map_err(ConfigError::Invalid)?;
+
+    let pts_suite = merged.pts_suite.unwrap_or_else(|| pts::SUITE_NAME.into());
+    let pts_columns = merged.pts_columns.unwrap_or_default();
+    validate_suite(&pts_suite, &pts_columns)?;
+
+    let browsers = merged.browsers.unwrap_or_default();
+    validate_browsers(&browsers)?;
+    let browser_suite_defs = merged.browser_suite_defs.unwrap_or_default();
+    browser_bench::select_suites(merged.browser_tests.as_deref(), &browser_suite_defs)
+        .map_err(ConfigError::Invalid)?;
+
+    let timeouts = merged.timeouts.unwrap_or_default();
+    validate_timeouts(&timeouts)?;
+
+    Ok(RunSettings {
+        config_path,
+        profile: name.clone(),
+        mode: merged.mode.unwrap_or_else(|| DEFAULT_MODE.into()),
+        pipeline: PipelineConfig {
+            sheet_id: merged.sheet_id,
+            drive_folder_id: merged.drive_folder_id,
+            csv_path: merged.csv_path.unwrap_or_else(|| DEFAULT_CSV_PATH.into()),
+            client_id: merged.client_id,
+            client_secret: merged.client_secret,
+            profile: name,
+            local_only: merged.local_only.unwrap_or(false),
+            pts_suite,
+            pts_tests: merged
+                .pts_tests
+                .unwrap_or_else(|| pts::DEFAULT_TESTS.iter().map(|t| t.to_string()).collect()),
+            pts_columns,
+            native_fallback: merged.native_fallback.unwrap_or(true),
+            pts_batch_setup: merged.pts_batch_setup.unwrap_or(true),
+            webdriver_url: merged
+                .webdriver_url
+                .unwrap_or_else(|| browser_bench::DEFAULT_WEBDRIVER_URL.into()),
+            webdriver: merged.webdriver.unwrap_or_default(),
+            browsers,
+            browser_suites: merged.browser_suites,
+            browser_tests: merged.browser_tests,
+            browser_suite_defs,
+            csv_stats: merged.csv_stats.unwrap_or(false),
+            only: merged.only,
+            skip: merged.skip.unwrap_or_default(),
+            output_json: merged.output_json,
+            timezone,
+            timeouts,
+        },
+    })
+}
+
+/// Settings a resumed run takes from its profile as the config file has
+/// it now, not from the checkpoint: `client_secret`, which is never saved,
+/// and `timeouts`, so a step that hit its limit can be resumed with a
+/// higher one. A run without a profile, or whose profile is gone, keeps
+/// what it has.
+pub fn refresh_for_resume(path: Option<&Path>, config: &mut PipelineConfig) -> Result<(), ConfigError> {
+    let config_path = path.map(Path::to_path_buf).unwrap_or_else(default_path);
+    let mut file = load(&config_path)?;
+    let Some(profile) = config.profile.as_deref().and_then(|name| file.profiles.remove(name)) else {
+        return Ok(());
+    };
+
+    let timeouts = profile.timeouts.unwrap_or_default();
+    validate_timeouts(&timeouts)?;
+    config.client_secret = profile.client_secret;
+    config.timeouts = timeouts;
+    Ok(())
+}
+
+/// PTS stores local suites as directories, so keep names path-safe.
+fn validate_suite(name: &str, columns: &[PtsColumn]) -> Result<(), ConfigError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(ConfigError::Invalid(format!(
+            "invalid pts_suite '{}': use lowercase letters, digits and '-'",
+            name
+        )));
+    }
+
+    for (i, col) in columns.iter().enumerate() {
+        if columns[..i].iter().any(|c| c.column == col.column) {
+            return Err(ConfigError::Invalid(format!(
+                "pts_columns defines column '{}' twice",
+                col.column
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Browser names label CSV columns, so they must be set and distinct.
+fn validate_browsers(browsers: &[BrowserTarget]) -> Result<(), ConfigError> {
+    for (i, b) in browsers.iter().enumerate() {
+        if b.name.trim().is_empty() {
+            return Err(ConfigError::Invalid(format!("browsers entry {} has an empty name", i + 1)));
+        }
+        if browsers[..i].iter().any(|other| other.name == b.name) {
+            return Err(ConfigError::Invalid(format!("browsers defines '{}' twice", b.name)));
+        }
+    }
+    Ok(())
+}
+
+/// A zero limit would fail every step at once; leave it unset instead.
+fn validate_timeouts(timeouts: &Timeouts) -> Result<(), ConfigError> {
+    for (name, secs) in timeouts.entries() {
+        if secs == Some(0) {
+            return Err(ConfigError::Invalid(format!(
+                "timeouts.{} must be at least 1 second; omit it for no limit",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn print_effective(settings: &RunSettings) {
+    let p = &settings.pipeline;
+    let exists = if settings.config_path.exists() { "" } else { " (not found)" };
+
+    println!("# config file: {}{}", settings.config_path.display(), exists);
+    println!("profile         = {}", settings.profile.as_deref().unwrap_or("(none)"));
+    println!("mode            = {}", settings.mode);
+    println!("csv_path        = {}", p.csv_path);
+    println!("sheet_id        = {}", opt(&p.sheet_id));
+    println!("drive_folder_id = {}", opt(&p.drive_folder_id));
+    println!("client_id       = {}", opt(&p.client_id));
+    println!(
+        "client_secret   = {}",
+        if p.client_secret.is_some() { "(set)" } else { "(unset)" }
+    );
+    println!("local_only      = {}", p.local_only);
+    println!("pts_suite       = {}", p.pts_suite);
+    println!("pts_tests       = [{}]", p.pts_tests.join(", "));
+    for col in &p.pts_columns {
+        println!(
+            "pts_columns     + {} <- {} [{}]",
+            col.column, col.test, col.filter()
+        );
+    }
+    println!("native_fallback = {}", p.native_fallback);
+    println!("pts_batch_setup = {}", p.pts_batch_setup);
+    println!("webdriver_url   = {}", p.webdriver_url);
+    println!("webdriver       = {}", p.webdriver.as_str());
+    for b in &p.browsers {
+        let (mode, url) = p.driver_for(b);
+        let driver = match mode {
+            WebDriverMode::External => url.to_string(),
+            mode => mode.as_str().to_string(),
+        };
+        println!(
+            "browsers        + {} via {} [{}]",
+            b.name,
+            driver,
+            b.capabilities.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    println!("browser_suites  = {}", p.browser_suites.as_deref().unwrap_or(browser_bench::BROWSERBENCH_URL));
+    let suites = p.browser_suite_list().unwrap_or_default();
+    let keys: Vec<&str> = suites.iter().map(|s| s.key.as_str()).collect();
+    println!("browser_tests   = [{}]", keys.join(", "));
+    for def in &p.browser_suite_defs {
+        println!("browser_suite_defs + {} ({}) <- {}", def.key, def.name, def.path);
+    }
+    println!("csv_stats       = {}", p.csv_stats);
+    println!("only            = {}", steps(p.only.as_deref().unwrap_or(&[])));
+    println!("skip            = {}", steps(&p.skip));
+    println!("output_json     = {}", opt(&p.output_json));
+    println!("timezone        = {}", p.timezone);
+    println!("timeouts        = {}", p.timeouts.describe());
+}
+
+fn steps(list: &[PipelineStep]) -> String {
+    let names: Vec<_> = list.iter().map(|s| format!("{:?}", s).to_lowercase()).collect();
+    format!("[{}]", names.join(", "))
+}
+
+fn opt(v: &Option<String>) -> &str {
+    v.as_deref().unwrap_or("(unset)")
+}
@@ -9,12 +9,22 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use crate::model::{BenchResults, DeviceSpecs};
+use crate::metadata::RunMetadata;
+use crate::model::{BenchResults, DeviceSpecs, MetricStats};
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 
-pub fn build_csv_row(specs: &DeviceSpecs, bench: &BenchResults) -> String {
-    // IMPORTANT: order must match your CSV header
+pub fn build_csv_row(
+    specs: &DeviceSpecs,
+    bench: &BenchResults,
+    meta: &RunMetadata,
+    stats: bool,
+) -> String {
+    let fio = bench.fio.clone().unwrap_or_default();
+
+    // IMPORTANT: order must match your CSV header. New columns go at the
+    // end so rows still line up in files written by older versions.
     let vals = [
         &specs.brand_model,
         &specs.launch_date,
@@ -70,45 +80,399 @@ pub fn build_csv_row(specs: &DeviceSpecs, bench: &BenchResults) -> String {
             .map(|v| v.to_string())
             .unwrap_or_default(),
         &bench.notes,
+        &meta.run_id,
+        &meta.started_at,
+        &fmt_opt_str(&meta.finished_at),
+        &meta.timezone,
+        &meta.mj_version,
+        &fmt_opt_str(&meta.pts_version),
+        &fmt_opt_str(&meta.browser_name),
+        &fmt_opt_str(&meta.browser_version),
+        &fmt_opt_str(&meta.driver_version),
+        &meta.kernel,
+        &meta.hostname,
+        &fmt_opt_str(&fio.seq_block_size),
+        &fmt_opt_str(&fio.rand_block_size),
+        &fmt_opt_str(&fio.test_path),
+        &bench.pts_source.map(|s| s.as_str().to_string()).unwrap_or_default(),
+        &fmt_opt(bench.native_compress_mb_s.as_ref()),
+        &fmt_opt(bench.native_aes_mb_s.as_ref()),
+        &fmt_opt_str(&bench.speedometer_version),
+        &fmt_opt_str(&bench.jetstream_version),
+        &fmt_opt_str(&bench.motionmark_version),
     ];
+    let extra = extra_columns(bench, stats);
 
     // Use csv crate for escaping
     let mut wtr = csv::WriterBuilder::new()
         .has_headers(false)
         .from_writer(vec![]);
-    wtr.write_record(vals.iter()) // iter over &str
+    wtr.write_record(vals.iter().map(|v| v.as_str()).chain(extra.iter().map(|(_, v)| v.as_str())))
         .expect("write_record failed");
     let data = wtr.into_inner().expect("into_inner failed");
     String::from_utf8_lossy(&data).trim_end().to_string()
 }
 
+const HEADER: &str = "Brand & Model,Launch Date,Price,CPU & Performance,Codename,CPU Speed,x86-64 Level,GPU,AI & NPU,RAM & Storage,Connectivity,Audio Ports,NFC & Wallet,Battery,Power & Charging,Qi Wireless Charging,Form Factor,Dimensions & Weight,Display,Build & Durability,Cameras,Biometrics & Health,Regional,Software & Updates,Color,Upgrade Options,Ecosystem Lock-in,Wear Detection,Touch Control,Storage Case,Special Features,Official Site,Info Links,BIOS/Boot Key,7-Zip MIPS,OpenSSL MB/s,RAMspeed MB/s,fio Seq Read MB/s,fio Seq Write MB/s,fio Rand Read IOPS,fio Rand Write IOPS,glmark2 Score,Kernel Build Time (s),Speedometer 2.1 Score,JetStream 2.2 Score,MotionMark 1.3 Score,Battery Full Capacity (Wh),Battery Design Capacity (Wh),Battery Health (%),Battery Cycle Count,Notes,Run ID,Started,Finished,Timezone,MJ-Benchmark Version,PTS Version,Browser,Browser Version,WebDriver Version,Kernel,Hostname,fio Seq Block Size,fio Rand Block Size,fio Test Path,PTS Source,Native Compression MB/s,Native AES-256 MB/s,Speedometer Version,JetStream Version,MotionMark Version";
+
+/// CSV header for rows built from `bench`: the fixed columns, then the
+/// columns from `extra_columns`.
+pub fn header(bench: &BenchResults, stats: bool) -> String {
+    let extra = extra_columns(bench, stats);
+    if extra.is_empty() {
+        return HEADER.to_string();
+    }
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+    wtr.write_record(extra.iter().map(|(label, _)| label.as_str()))
+        .expect("write_record failed");
+    let data = wtr.into_inner().expect("into_inner failed");
+    format!("{},{}", HEADER, String::from_utf8_lossy(&data).trim_end())
+}
+
+/// (label, value) for the columns after the fixed ones: one per
+/// `pts_columns` custom column; score and version of each browser suite
+/// without a fixed column; with more than one configured browser, each
+/// browser's name, version, engine and suite scores; then with `stats`
+/// the trial count, mean, std dev, min and max of every PTS metric.
+fn extra_columns(bench: &BenchResults, stats: bool) -> Vec<(String, String)> {
+    let mut cols: Vec<(String, String)> = bench
+        .custom
+        .iter()
+        .map(|c| (c.label.clone(), fmt_opt(c.value.as_ref())))
+        .collect();
+
+    for suite in bench.extra_suites() {
+        cols.push((format!("{} Score", suite.name), fmt_opt(suite.score.as_ref())));
+        cols.push((format!("{} Version", suite.name), fmt_opt_str(&suite.version)));
+    }
+
+    // A single browser is already in the Browser and score columns.
+    if bench.browsers.len() > 1 {
+        for b in &bench.browsers {
+            cols.push((format!("Browser ({})", b.name), fmt_opt_str(&b.browser_name)));
+            cols.push((format!("Browser Version ({})", b.name), fmt_opt_str(&b.browser_version)));
+            cols.push((format!("Browser Engine ({})", b.name), fmt_opt_str(&b.engine)));
+            for suite in &b.suites {
+                cols.push((
+                    format!("{} Score ({})", suite.name, b.name),
+                    fmt_opt(suite.score.as_ref()),
+                ));
+            }
+        }
+    }
+
+    if stats {
+        for m in bench.pts_metrics() {
+            let s = m.stats.as_ref();
+            let stat = |f: fn(&MetricStats) -> f64| fmt_opt(s.map(f).as_ref());
+            cols.push((
+                format!("{} Trials", m.label),
+                s.map(|s| s.trials.to_string()).unwrap_or_default(),
+            ));
+            cols.push((format!("{} Mean", m.label), stat(|s| s.mean)));
+            cols.push((format!("{} Std Dev", m.label), stat(|s| s.std_dev)));
+            cols.push((format!("{} Min", m.label), stat(|s| s.min)));
+            cols.push((format!("{} Max", m.label), stat(|s| s.max)));
+        }
+    }
+
+    cols
+}
+
 fn fmt_opt(v: Option<&f64>) -> String {
     v.map(|x| format!("{}", x)).unwrap_or_default()
 }
 
-pub fn append_to_csv(path: &str, row: &str) -> Result<(), std::io::Error> {
-    let needs_header = match File::open(path) {
+fn fmt_opt_str(v: &Option<String>) -> String {
+    v.clone().unwrap_or_default()
+}
+
+/// First line of `path`, or `None` if the file is missing or empty.
+fn existing_header(path: &str) -> Result<Option<String>, std::io::Error> {
+    match File::open(path) {
         Ok(f) => {
             let mut reader = BufReader::new(f);
             let mut first_line = String::new();
             reader.read_line(&mut first_line)?;
-            first_line.trim().is_empty()
+            let line = first_line.trim_start_matches('\u{feff}').trim_end_matches(['\r', '\n']);
+            Ok(Some(line.to_string()).filter(|l| !l.trim().is_empty()))
         }
-        Err(_) => true,
+        Err(_) => Ok(None),
+    }
+}
+
+/// Values of the "Run ID" column in `path`; empty if the file is missing
+/// or has no such column.
+pub fn run_ids(path: &str) -> Result<HashSet<String>, std::io::Error> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file);
+    let Some(column) = reader.headers()?.iter().position(|h| h == "Run ID") else {
+        return Ok(HashSet::new());
     };
+    let mut ids = HashSet::new();
+    for record in reader.records() {
+        if let Some(id) = record?.get(column).filter(|id| !id.is_empty()) {
+            ids.insert(id.to_string());
+        }
+    }
+    Ok(ids)
+}
+
+/// Column labels of one header line.
+fn labels(line: &str) -> Vec<String> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(line.as_bytes())
+        .records()
+        .next()
+        .and_then(Result::ok)
+        .map(|r| r.iter().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Number of columns `existing` lacks if it was written by a version from
+/// before some of the fixed columns: its labels are a strict prefix of the
+/// fixed ones, and `expected` starts with them.
+fn missing_columns(existing: &str, expected: &str) -> Option<usize> {
+    let (old, new) = (labels(existing), labels(expected));
+    (old.len() < labels(HEADER).len() && new.starts_with(&old)).then(|| new.len() - old.len())
+}
+
+/// Rewrite `path` under `header`, padding its rows with empty cells, so rows
+/// under the new columns line up. Goes through a temporary file, so a failure
+/// leaves the original as it was.
+fn upgrade_header(path: &str, header: &str) -> Result<(), std::io::Error> {
+    let width = labels(header).len();
+    let tmp = format!("{}.mj-upgrade", path);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut out = File::create(&tmp)?;
+    writeln!(out, "{}", header)?;
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(out);
+    for record in reader.records().skip(1) {
+        let mut cells: Vec<String> = record?.iter().map(str::to_string).collect();
+        if cells.len() < width {
+            cells.resize(width, String::new());
+        }
+        wtr.write_record(&cells)?;
+    }
+    wtr.flush()?;
+    drop(wtr);
+    std::fs::rename(&tmp, path)
+}
+
+/// Why rows under `expected` can't go below `existing`, naming the first
+/// column that differs.
+fn header_mismatch(existing: &str, expected: &str) -> String {
+    let (old, new) = (labels(existing), labels(expected));
+    let at = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let column = |cols: &[String]| cols.get(at).map_or("nothing".to_string(), |c| format!("\"{}\"", c));
+    format!(
+        "its header does not match this run's columns ({} columns there, {} here; column {} is {} there, {} here)",
+        old.len(),
+        new.len(),
+        at + 1,
+        column(&old),
+        column(&new)
+    )
+}
+
+/// Human-readable description of what `append_to_csv` will do with `path`
+/// for rows under `header`.
+pub fn header_status(path: &str, header: &str) -> String {
+    if !std::path::Path::new(path).exists() {
+        return "does not exist; will be created with header".into();
+    }
+    match existing_header(path) {
+        Ok(None) => "exists but is empty; header will be written".into(),
+        Ok(Some(h)) if h == header => "exists with header; row will be appended".into(),
+        Ok(Some(h)) => match missing_columns(&h, header) {
+            Some(n) => format!("exists with an older header; it gains {} columns, then the row will be appended", n),
+            None => format!("cannot be appended to: {}", header_mismatch(&h, header)),
+        },
+        Err(e) => format!("exists but cannot be read: {}", e),
+    }
+}
+
+/// Append `row`, writing the header first into a new or empty file. A file
+/// from before some of the fixed columns gets them once, its rows padded
+/// with empty cells. Any other header difference (other `pts_columns`,
+/// browsers or `csv_stats`) leaves the file alone: its rows would no longer
+/// line up.
+pub fn append_to_csv(
+    path: &str,
+    bench: &BenchResults,
+    stats: bool,
+    row: &str,
+) -> Result<(), std::io::Error> {
+    let header = header(bench, stats);
+    let existing = existing_header(path)?;
+    if let Some(existing) = &existing {
+        if *existing != header {
+            if missing_columns(existing, &header).is_none() {
+                return Err(std::io::Error::other(header_mismatch(existing, &header)));
+            }
+            upgrade_header(path, &header)?;
+        }
+    }
 
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
         .open(path)?;
 
-    if needs_header {
-        writeln!(
-            file,
-            "Brand & Model,Launch Date,Price,CPU & Performance,Codename,CPU Speed,x86-64 Level,GPU,AI & NPU,RAM & Storage,Connectivity,Audio Ports,NFC & Wallet,Battery,Power & Charging,Qi Wireless Charging,Form Factor,Dimensions & Weight,Display,Build & Durability,Cameras,Biometrics & Health,Regional,Software & Updates,Color,Upgrade Options,Ecosystem Lock-in,Wear Detection,Touch Control,Storage Case,Special Features,Official Site,Info Links,BIOS/Boot Key,7-Zip MIPS,OpenSSL MB/s,RAMspeed MB/s,fio Seq Read MB/s,fio Seq Write MB/s,fio Rand Read IOPS,fio Rand Write IOPS,glmark2 Score,Kernel Build Time (s),Speedometer 2.1 Score,JetStream 2.2 Score,MotionMark 1.3 Score,Battery Full Capacity (Wh),Battery Design Capacity (Wh),Battery Health (%),Battery Cycle Count,Notes"
-        )?;
+    if existing.is_none() {
+        writeln!(file, "{}", header)?;
     }
 
     writeln!(file, "{}", row)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BrowserScores, CustomMetric, SuiteScore};
+
+    fn append(path: &str, bench: &BenchResults, stats: bool) -> Result<(), std::io::Error> {
+        let row = build_csv_row(&DeviceSpecs::default(), bench, &RunMetadata::new("UTC"), stats);
+        append_to_csv(path, bench, stats, &row)
+    }
+
+    #[test]
+    fn rows_only_go_under_a_matching_header() {
+        let path = std::env::temp_dir().join(format!("mj-csv-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let bench = BenchResults::default();
+
+        append(path, &bench, false).unwrap();
+        append(path, &bench, false).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 3);
+
+        let renamed = HEADER.replace(",Notes,", ",Comments,");
+        std::fs::write(path, format!("{}\n", renamed)).unwrap();
+        let err = append(path, &bench, false).unwrap_err().to_string();
+        assert!(err.contains("\"Comments\" there, \"Notes\" here"), "{}", err);
+        assert!(header_status(path, &header(&bench, false)).starts_with("cannot be appended to"));
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn files_from_before_the_run_metadata_columns_get_them_once() {
+        let path = std::env::temp_dir().join(format!("mj-csv-old-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let bench = BenchResults::default();
+        let old = HEADER.split(",Run ID,").next().unwrap();
+        let old_width = labels(old).len();
+        let old_row = format!("\"Old, Laptop\"{}", ",".repeat(old_width - 1));
+        std::fs::write(path, format!("{}\n{}\n", old, old_row)).unwrap();
+
+        let expected = header(&bench, false);
+        let status = header_status(path, &expected);
+        assert!(status.starts_with("exists with an older header"), "{}", status);
+        append(path, &bench, false).unwrap();
+        append(path, &bench, false).unwrap();
+
+        let text = std::fs::read_to_string(path).unwrap();
+        assert_eq!(text.lines().next().unwrap(), expected);
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let rows: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|r| r.len() == labels(&expected).len()));
+        assert_eq!(&rows[0][0], "Old, Laptop");
+        let run_id = labels(&expected).iter().position(|l| l == "Run ID").unwrap();
+        assert!(rows[0][run_id].is_empty() && !rows[1][run_id].is_empty());
+        assert!(!std::path::Path::new(&format!("{}.mj-upgrade", path)).exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn csv_stats_cannot_be_toggled_on_an_existing_file() {
+        let path = std::env::temp_dir().join(format!("mj-csv-stats-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let bench = BenchResults::default();
+
+        append(path, &bench, false).unwrap();
+        let err = append(path, &bench, true).unwrap_err().to_string();
+        assert!(err.contains("is nothing there") && err.contains(" Trials\" here"), "{}", err);
+
+        std::fs::remove_file(path).unwrap();
+        append(path, &bench, true).unwrap();
+        let err = append(path, &bench, false).unwrap_err().to_string();
+        assert!(err.contains(" Trials\" there, nothing here"), "{}", err);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn profiles_with_other_custom_columns_need_their_own_file() {
+        let path = std::env::temp_dir().join(format!("mj-csv-custom-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let custom = |label: &str| BenchResults {
+            custom: vec![CustomMetric {
+                label: label.into(),
+                unit: "fps".into(),
+                value: Some(42.0),
+            }],
+            ..BenchResults::default()
+        };
+
+        append(path, &custom("x264 Bosphorus 4K"), false).unwrap();
+        append(path, &custom("x264 Bosphorus 4K"), false).unwrap();
+        let err = append(path, &custom("x265 Bosphorus 4K"), false).unwrap_err().to_string();
+        assert!(err.contains("\"x264 Bosphorus 4K\" there, \"x265 Bosphorus 4K\" here"), "{}", err);
+        assert!(append(path, &BenchResults::default(), false).is_err());
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 3);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn browser_lists_cannot_change_on_an_existing_file() {
+        let path = std::env::temp_dir().join(format!("mj-csv-browsers-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let browsers = |names: &[&str]| BenchResults {
+            browsers: names
+                .iter()
+                .map(|name| BrowserScores {
+                    name: name.to_string(),
+                    suites: vec![SuiteScore {
+                        key: "speedometer".into(),
+                        name: "Speedometer 2.1".into(),
+                        unit: "runs/min".into(),
+                        ..SuiteScore::default()
+                    }],
+                    ..BrowserScores::default()
+                })
+                .collect(),
+            ..BenchResults::default()
+        };
+
+        append(path, &browsers(&["chrome", "firefox"]), false).unwrap();
+        let err = append(path, &browsers(&["chrome", "safari"]), false).unwrap_err().to_string();
+        assert!(err.contains("\"Browser (firefox)\" there, \"Browser (safari)\" here"), "{}", err);
+        let err = append(path, &browsers(&["chrome", "firefox", "safari"]), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("nothing there, \"Browser (safari)\" here"), "{}", err);
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Crate-wide error type and process exit codes.
+//!
+//! | code | failure                                   |
+//! |------|-------------------------------------------|
+//! | 0    | success                                   |
+//! | 1    | other / internal error                    |
+//! | 2    | invalid command line (reported by clap)   |
+//! | 3    | invalid config file or profile            |
+//! | 4    | missing or unreadable run checkpoint      |
+//! | 5    | step, PTS test or browser suite timed out |
+//! | 10   | dependency install failed                 |
+//! | 11   | spec collection failed                    |
+//! | 20   | Phoronix Test Suite not installed         |
+//! | 21   | PTS run or result parsing failed          |
+//! | 22   | invalid specs input for `import-pts`      |
+//! | 23   | native micro-benchmarks failed            |
+//! | 30   | browser benchmarks failed (WebDriver)     |
+//! | 40   | CSV write failed                          |
+//! | 50   | Google authentication failed              |
+//! | 51   | Google Sheets sync failed                 |
+//! | 52   | Google Drive upload failed                |
+//! | 130  | cancelled by the user (q / Ctrl-C)        |
+//!
+//! Codes are a stable interface for scripts: add, don't renumber.
+
+use thiserror::Error;
+
+use crate::browser_bench::BrowserError;
+use crate::checkpoint::CheckpointError;
+use crate::config::ConfigError;
+use crate::google_auth::GoogleAuthError;
+use crate::google_drive::DriveError;
+use crate::google_sheets::SheetsError;
+use crate::install::InstallError;
+use crate::native_bench::NativeError;
+use crate::pts::PtsError;
+
+#[derive(Debug, Clone, Error)]
+pub enum MjError {
+    #[error("install failed: {0}")]
+    Install(#[from] InstallError),
+    #[error("spec collection failed: {0}")]
+    Specs(String),
+    #[error("PTS: {0}")]
+    Pts(#[from] PtsError),
+    #[error("import: {0}")]
+    Import(String),
+    #[error("native benchmarks: {0}")]
+    Native(#[from] NativeError),
+    #[error("browser benchmarks: {0}")]
+    Browser(#[from] BrowserError),
+    #[error("could not write CSV {0}: {1}")]
+    Csv(String, String),
+    #[error("Google authentication: {0}")]
+    Auth(#[from] GoogleAuthError),
+    #[error("Google Sheets: {0}")]
+    Sheets(#[from] SheetsError),
+    #[error("Google Drive: {0}")]
+    Drive(#[from] DriveError),
+    #[error("config: {0}")]
+    Config(#[from] ConfigError),
+    #[error("resume: {0}")]
+    Checkpoint(#[from] CheckpointError),
+    #[error("{step} step exceeded its {limit_s}s timeout")]
+    Timeout { step: String, limit_s: u64 },
+    #[error("cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl MjError {
+    /// Process exit code for this failure; see the table above.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            MjError::Other(_) => 1,
+            MjError::Config(_) => 3,
+            MjError::Checkpoint(_) => 4,
+            MjError::Timeout { .. }
+            | MjError::Pts(PtsError::TestTimedOut { .. })
+            | MjError::Browser(BrowserError::SuiteTimedOut { .. }) => 5,
+            MjError::Install(_) => 10,
+            MjError::Specs(_) => 11,
+            MjError::Pts(PtsError::MissingPts) => 20,
+            MjError::Pts(_) => 21,
+            MjError::Import(_) => 22,
+            MjError::Native(_) => 23,
+            MjError::Browser(_) => 30,
+            MjError::Csv(..) => 40,
+            MjError::Auth(_) => 50,
+            MjError::Sheets(_) => 51,
+            MjError::Drive(_) => 52,
+            MjError::Cancelled => 130,
+        }
+    }
+
+    /// One line telling the user how to get past this failure.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            MjError::Other(_) => "re-run with --mode cli to see the full log",
+            MjError::Config(_) => {
+                "fix ~/.mj_bench/config.toml (or --config); `mj-benchmark config show` prints the merged settings"
+            }
+            MjError::Checkpoint(_) => {
+                "check the run ID; saved runs live in ~/.mj_bench/runs/, or start over with `mj-benchmark run`"
+            }
+            MjError::Timeout { .. }
+            | MjError::Pts(PtsError::TestTimedOut { .. })
+            | MjError::Browser(BrowserError::SuiteTimedOut { .. }) => {
+                "raise [profiles.<name>.timeouts] in the config, then `mj-benchmark resume <run-id>` (it reads them again)"
+            }
+            MjError::Cancelled => "`mj-benchmark resume <run-id>` continues from the cancelled step",
+            MjError::Install(_) => {
+                "install the dependencies by hand; `mj-benchmark plan-install` lists the commands"
+            }
+            MjError::Specs(_) => "run `mj-benchmark specs` to see which collector fails, or pass --skip specs",
+            MjError::Pts(PtsError::MissingPts) => {
+                "install the Phoronix Test Suite (`mj-benchmark install --execute`) or pass --skip pts"
+            }
+            MjError::Pts(PtsError::UserConfig(..)) => {
+                "fix or delete PTS's user-config.xml (a .mj-backup next to it is the original), or pass --no-pts-batch-setup"
+            }
+            MjError::Pts(_) => {
+                "check the PTS output above, then `mj-benchmark resume <run-id>` or pass --skip pts"
+            }
+            MjError::Import(_) => {
+                "--specs takes `mj-benchmark specs --format json` output; --spec keys are the field names it prints"
+            }
+            MjError::Native(_) => {
+                "set TMPDIR to a writable directory, install the Phoronix Test Suite, or pass --skip pts"
+            }
+            MjError::Browser(BrowserError::DriverNotFound(_) | BrowserError::DriverStart(..)) => {
+                "install chromedriver or geckodriver on PATH, or run one yourself and pass --webdriver external"
+            }
+            MjError::Browser(BrowserError::LocalSuites(..)) => {
+                "--browser-suites needs a directory per selected suite (e.g. Speedometer2.1/) with its start page"
+            }
+            MjError::Browser(_) => {
+                "start chromedriver or geckodriver on --webdriver-url, pass --webdriver auto, or pass --skip browser"
+            }
+            MjError::Csv(..) => {
+                "check that --csv-path is writable, then `mj-benchmark resume <run-id>`; if its header differs, add --csv-path <new.csv>"
+            }
+            MjError::Auth(_) => {
+                "check --client-id/--client-secret or delete ~/.mj_bench/google_token.json; --local-only skips Google sync"
+            }
+            MjError::Sheets(_) => {
+                "check --sheet-id and that the account can edit the sheet, then `mj-benchmark resume <run-id>`"
+            }
+            MjError::Drive(_) => {
+                "check --drive-folder-id and that the account can write to it, then `mj-benchmark resume <run-id>`"
+            }
+        }
+    }
+}
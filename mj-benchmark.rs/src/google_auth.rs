@@ -17,21 +17,26 @@ use serde::{Deserialize, Serialize};
 use std::{fs, net::TcpListener, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum GoogleAuthError {
     #[error("OAuth error: {0}")]
     OAuth(String),
     #[error("IO error: {0}")]
     Io(String),
+    #[error("missing client credentials (--client-id and --client-secret)")]
+    MissingCredentials,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredToken {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_at: u64,
 }
 
+pub const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+pub const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
 fn token_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap();
     PathBuf::from(format!("{}/.mj_bench/google_token.json", home))
@@ -83,8 +88,8 @@ async fn oauth_flow(
     let client = BasicClient::new(
         ClientId::new(client_id.into()),
         Some(ClientSecret::new(client_secret.into())),
-        AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".into()).unwrap(),
-        Some(TokenUrl::new("https://oauth2.googleapis.com/token".into()).unwrap()),
+        AuthUrl::new(AUTH_URL.into()).unwrap(),
+        Some(TokenUrl::new(TOKEN_URL.into()).unwrap()),
     )
     .set_redirect_uri(RedirectUrl::new(redirect.into()).unwrap());
 
@@ -143,8 +148,8 @@ async fn refresh_token(
     let client = BasicClient::new(
         ClientId::new(client_id.into()),
         Some(ClientSecret::new(client_secret.into())),
-        AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".into()).unwrap(),
-        Some(TokenUrl::new("https://oauth2.googleapis.com/token".into()).unwrap()),
+        AuthUrl::new(AUTH_URL.into()).unwrap(),
+        Some(TokenUrl::new(TOKEN_URL.into()).unwrap()),
     );
 
     let token = client
@@ -15,12 +15,14 @@ use serde_json::json;
 use std::fs;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum DriveError {
     #[error("HTTP error: {0}")]
     Http(String),
 }
 
+pub const UPLOAD_URL: &str = "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart";
+
 pub async fn upload_csv(
     folder_id: &str,
     csv_path: &str,
@@ -34,7 +36,7 @@ pub async fn upload_csv(
         "parents": [folder_id]
     });
 
-    let url = "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart";
+    let url = UPLOAD_URL;
 
     let form = reqwest::multipart::Form::new()
         .part(
@@ -14,12 +14,19 @@ use reqwest::Client;
 use serde_json::json;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum SheetsError {
     #[error("HTTP error: {0}")]
     Http(String),
 }
 
+pub fn append_url(sheet_id: &str) -> String {
+    format!(
+        "https://sheets.googleapis.com/v4/spreadsheets/{}/values/A1:append?valueInputOption=USER_ENTERED",
+        sheet_id
+    )
+}
+
 pub async fn append_row(
     sheet_id: &str,
     row: &str,
@@ -27,10 +34,7 @@ pub async fn append_row(
 ) -> Result<(), SheetsError> {
     let client = Client::new();
 
-    let url = format!(
-        "https://sheets.googleapis.com/v4/spreadsheets/{}/values/A1:append?valueInputOption=USER_ENTERED",
-        sheet_id
-    );
+    let url = append_url(sheet_id);
 
     let body = json!({
         "values": [
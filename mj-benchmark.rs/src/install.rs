@@ -13,7 +13,7 @@ use crate::platform::Platform;
 use thiserror::Error;
 use std::process::Command;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum InstallError {
     #[error("unsupported platform for install")]
     UnsupportedPlatform,
@@ -9,8 +9,9 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use clap::{Parser, Subcommand};
-use std::error::Error;
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 mod platform;
 mod install;
@@ -18,21 +19,45 @@ mod specs_linux;
 mod specs_macos;
 mod specs_bsd;
 mod specs_windows;
+mod process;
 mod pts;
+mod pts_results;
+mod pts_progress;
+mod pts_batch;
+mod pts_import;
+mod native_bench;
 mod browser_bench;
+mod webdriver;
+mod suite_server;
 mod model;
 mod csv_row;
 mod google_auth;
 mod google_sheets;
 mod google_drive;
+mod sync;
+mod pipeline;
+mod checkpoint;
+mod report;
+mod metadata;
+mod specs_output;
+mod config;
+mod error;
 mod tui;
 
 use platform::Platform;
-use model::{DeviceSpecs, BenchResults};
+use model::DeviceSpecs;
+use pipeline::{PipelineEvent, PipelineStep, RunStart, StepOutcome};
+use webdriver::WebDriverMode;
+use config::RunSettings;
+use error::MjError;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Cli {
+    /// Config file (default: ~/.mj_bench/config.toml)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -44,40 +69,266 @@ enum Commands {
         execute: bool,
     },
 
-    Run {
-        #[arg(long)]
-        sheet_id: String,
+    Run(RunArgs),
 
-        #[arg(long)]
-        drive_folder_id: String,
+    /// Continue an interrupted run at its first unfinished step
+    Resume {
+        run_id: String,
 
-        #[arg(long, default_value = "mj_benchmarks.csv")]
-        csv_path: String,
+        /// tui (default) or cli
+        #[arg(long, default_value = config::DEFAULT_MODE)]
+        mode: String,
 
+        /// Needed again if the run got it on the command line; checkpoints
+        /// don't store it
         #[arg(long)]
-        client_id: String,
+        client_secret: Option<String>,
 
+        /// Write the CSV row to this file instead, e.g. when the run's file
+        /// has another header
         #[arg(long)]
-        client_secret: String,
+        csv_path: Option<String>,
+    },
 
-        /// tui (default) or cli
-        #[arg(long, default_value = "tui")]
-        mode: String,
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Append CSV rows for saved PTS results without re-running them
+    ImportPts(ImportArgs),
+
+    /// Collect and print device specs without running the pipeline
+    Specs {
+        #[arg(long, value_enum, default_value = "table")]
+        format: specs_output::SpecsFormat,
     },
 
     Detect,
     PlanInstall,
 }
 
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective settings after merging config and flags
+    Show(RunArgs),
+}
+
+/// Flags left unset fall back to the selected config profile, then to
+/// built-in defaults.
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// Named profile from the config file
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Google Sheet to append to; Sheets sync is skipped without it
+    #[arg(long)]
+    sheet_id: Option<String>,
+
+    /// Google Drive folder to upload to; Drive upload is skipped without it
+    #[arg(long)]
+    drive_folder_id: Option<String>,
+
+    /// [default: mj_benchmarks.csv]
+    #[arg(long)]
+    csv_path: Option<String>,
+
+    #[arg(long)]
+    client_id: Option<String>,
+
+    #[arg(long)]
+    client_secret: Option<String>,
+
+    /// Skip every Google sync step, even if targets are given
+    #[arg(long, overrides_with = "no_local_only")]
+    local_only: bool,
+
+    /// Sync to Google even if the profile sets local_only
+    #[arg(long, overrides_with = "local_only")]
+    no_local_only: bool,
+
+    /// tui (default) or cli
+    #[arg(long)]
+    mode: Option<String>,
+
+    /// Local PTS suite to build and run [default: mohamed-core]
+    #[arg(long)]
+    pts_suite: Option<String>,
+
+    /// Comma-separated PTS test profiles for the suite
+    #[arg(long, value_delimiter = ',')]
+    pts_tests: Option<Vec<String>>,
+
+    /// Fail the PTS step when phoronix-test-suite is missing instead of
+    /// running the built-in native micro-benchmarks
+    #[arg(long)]
+    no_native_fallback: bool,
+
+    /// Leave PTS's user-config.xml alone instead of patching its batch
+    /// settings for the run and restoring it afterwards
+    #[arg(long)]
+    no_pts_batch_setup: bool,
+
+    /// WebDriver endpoint [default: http://localhost:9515]
+    #[arg(long)]
+    webdriver_url: Option<String>,
+
+    /// external (connect to --webdriver-url), or auto, chromedriver or
+    /// geckodriver to start that driver for the run [default: external]
+    #[arg(long, value_name = "MODE")]
+    webdriver: Option<WebDriverMode>,
+
+    /// Serve the browser suites from this local copy (Speedometer2.1/,
+    /// JetStream2.2/, MotionMark1.3/) instead of browserbench.org
+    #[arg(long, value_name = "DIR")]
+    browser_suites: Option<String>,
+
+    /// Comma-separated browser suites to run, e.g.
+    /// speedometer3,jetstream,motionmark [default: speedometer,jetstream,motionmark]
+    #[arg(long, value_delimiter = ',')]
+    browser_tests: Option<Vec<String>>,
+
+    /// Add PTS trial statistics (trials, mean, std dev, min, max) columns
+    /// to the CSV
+    #[arg(long, overrides_with = "no_csv_stats")]
+    csv_stats: bool,
+
+    /// Leave the statistics columns out even if the profile sets csv_stats
+    #[arg(long, overrides_with = "csv_stats")]
+    no_csv_stats: bool,
+
+    /// Run only these measurement steps (specs,pts,browser); CSV and sync
+    /// steps still run unless skipped
+    #[arg(long, value_delimiter = ',')]
+    only: Option<Vec<PipelineStep>>,
+
+    /// Steps to skip (specs,pts,browser,csv,sheets,drive)
+    #[arg(long, value_delimiter = ',')]
+    skip: Option<Vec<PipelineStep>>,
+
+    /// Write a structured JSON report (specs, metrics, step timings, errors)
+    #[arg(long)]
+    output_json: Option<String>,
+
+    /// IANA timezone for run timestamps, e.g. Asia/Riyadh [default: UTC]
+    #[arg(long)]
+    timezone: Option<String>,
+
+    /// Print what the run would do without running anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// Saved result names (from ~/.phoronix-test-suite/test-results),
+    /// result directories or composite.xml files
+    #[arg(required = true)]
+    results: Vec<String>,
+
+    /// Device specs for the machine the results came from, as written by
+    /// `mj-benchmark specs --format json`
+    #[arg(long)]
+    specs: Option<PathBuf>,
+
+    /// Set one spec field, e.g. --spec cpu="AMD Ryzen 7 7840U"; repeatable
+    #[arg(long = "spec", value_name = "KEY=VALUE", value_parser = pts_import::parse_spec_override)]
+    spec_overrides: Vec<(String, String)>,
+
+    /// Named profile from the config file (CSV path, pts_columns, timezone)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// [default: mj_benchmarks.csv]
+    #[arg(long)]
+    csv_path: Option<String>,
+
+    /// Add PTS trial statistics columns to the CSV
+    #[arg(long, overrides_with = "no_csv_stats")]
+    csv_stats: bool,
+
+    /// Leave the statistics columns out even if the profile sets csv_stats
+    #[arg(long, overrides_with = "csv_stats")]
+    no_csv_stats: bool,
+
+    /// IANA timezone the results were recorded in [default: UTC]
+    #[arg(long)]
+    timezone: Option<String>,
+}
+
+impl RunArgs {
+    fn resolve(self, config_path: Option<&Path>) -> Result<RunSettings, config::ConfigError> {
+        let overrides = config::Profile {
+            sheet_id: self.sheet_id,
+            drive_folder_id: self.drive_folder_id,
+            csv_path: self.csv_path,
+            client_id: self.client_id,
+            client_secret: self.client_secret,
+            local_only: switch(self.local_only, self.no_local_only),
+            mode: self.mode,
+            pts_suite: self.pts_suite,
+            pts_tests: self.pts_tests,
+            pts_columns: None,
+            native_fallback: self.no_native_fallback.then_some(false),
+            pts_batch_setup: self.no_pts_batch_setup.then_some(false),
+            webdriver_url: self.webdriver_url,
+            webdriver: self.webdriver,
+            browsers: None,
+            browser_suites: self.browser_suites,
+            browser_tests: self.browser_tests,
+            browser_suite_defs: None,
+            csv_stats: switch(self.csv_stats, self.no_csv_stats),
+            only: self.only,
+            skip: self.skip,
+            output_json: self.output_json,
+            timezone: self.timezone,
+            timeouts: None,
+        };
+        config::resolve(config_path, self.profile.as_deref(), overrides)
+    }
+}
+
+/// `Some(true)` for `--flag`, `Some(false)` for `--no-flag`, `None` (use
+/// the profile) for neither; clap keeps only the last of the two.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    if on {
+        Some(true)
+    } else if off {
+        Some(false)
+    } else {
+        None
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    let result = run(cli).await;
+    // Children cancelled on the way out may still be due a SIGKILL.
+    let _ = tokio::task::spawn_blocking(process::wait_for_groups).await;
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("hint: {}", e.hint());
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), MjError> {
     match cli.command {
         Commands::Detect => {
             println!("Detected platform: {}", platform::detect_platform());
         }
 
+        Commands::Specs { format } => {
+            let specs = collect_specs(platform::detect_platform());
+            specs_output::print_specs(&specs, format).map_err(|e| MjError::Other(e.to_string()))?;
+        }
+
         Commands::PlanInstall => {
             let p = platform::detect_platform();
             println!("Detected platform: {}", p);
@@ -94,40 +345,64 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
         }
 
-        Commands::Run {
-            sheet_id,
-            drive_folder_id,
-            csv_path,
-            client_id,
-            client_secret,
-            mode,
-        } => {
-            let want_tui = mode == "tui";
-            let use_tui = want_tui && is_tty_stdout() && !is_ci_env();
-
-            if want_tui && !use_tui {
-                eprintln!("No usable TTY detected — falling back to CLI mode.");
+        Commands::Run(args) => {
+            let dry_run = args.dry_run;
+            let settings = args.resolve(cli.config.as_deref())?;
+            if dry_run {
+                pipeline::print_plan(&settings.pipeline)?;
+                return Ok(());
             }
+            run_pipeline(&settings.mode, RunStart::New(Box::new(settings.pipeline))).await?;
+        }
 
-            if use_tui {
-                tui::app::run_full_pipeline_with_tui(
-                    &sheet_id,
-                    &drive_folder_id,
-                    &csv_path,
-                    &client_id,
-                    &client_secret,
-                )
-                .await?;
-            } else {
-                run_full_pipeline_cli(
-                    &sheet_id,
-                    &drive_folder_id,
-                    &csv_path,
-                    &client_id,
-                    &client_secret,
-                )
-                .await?;
+        Commands::Resume { run_id, mode, client_secret, csv_path } => {
+            let mut checkpoint = checkpoint::Checkpoint::load(&run_id)?;
+            config::refresh_for_resume(cli.config.as_deref(), &mut checkpoint.config)?;
+            if client_secret.is_some() {
+                checkpoint.config.client_secret = client_secret;
+            }
+            if let Some(path) = csv_path {
+                checkpoint.config.csv_path = path;
             }
+            run_pipeline(&mode, RunStart::Resume(Box::new(checkpoint))).await?;
+        }
+
+        Commands::ImportPts(args) => {
+            let overrides = config::Profile {
+                csv_path: args.csv_path,
+                csv_stats: switch(args.csv_stats, args.no_csv_stats),
+                timezone: args.timezone,
+                ..config::Profile::default()
+            };
+            let settings = config::resolve(cli.config.as_deref(), args.profile.as_deref(), overrides)?;
+            let specs = pts_import::load_specs(args.specs.as_deref(), &args.spec_overrides)?;
+
+            let imported = pts_import::import_all(&args.results, &specs, &settings.pipeline)?;
+            for item in &imported {
+                if item.duplicate {
+                    println!(
+                        "– {} already in {} (run {}); skipped",
+                        item.path.display(),
+                        settings.pipeline.csv_path,
+                        item.metadata.run_id
+                    );
+                    continue;
+                }
+                for missing in &item.missing {
+                    eprintln!("warning: {}: {}", item.path.display(), missing);
+                }
+                println!(
+                    "✔ {} -> {} (run {})",
+                    item.path.display(),
+                    settings.pipeline.csv_path,
+                    item.metadata.run_id
+                );
+            }
+        }
+
+        Commands::Config { action: ConfigAction::Show(args) } => {
+            let settings = args.resolve(cli.config.as_deref())?;
+            config::print_effective(&settings);
         }
     }
 
@@ -146,36 +421,102 @@ fn is_ci_env() -> bool {
         || std::env::var("BUILD_BUILDID").is_ok()
 }
 
-async fn run_full_pipeline_cli(
-    sheet_id: &str,
-    drive_folder_id: &str,
-    csv_path: &str,
-    client_id: &str,
-    client_secret: &str,
-) -> Result<(), Box<dyn Error>> {
-    let platform = platform::detect_platform();
-    println!("Platform: {}", platform);
-
-    println!("Collecting specs…");
-    let specs = collect_specs(platform);
-
-    println!("Running PTS benchmarks…");
-    pts::ensure_pts_installed()?;
-    pts::ensure_suite_exists()?;
-    let mut bench = pts::run_suite()?;
-
-    println!("Running browser benchmarks…");
-    let browser = browser_bench::run_browser_benchmarks().await;
-    bench.speedometer_score = browser.speedometer;
-    bench.jetstream_score = browser.jetstream;
-    bench.motionmark_score = browser.motionmark;
-
-    let row = csv_row::build_csv_row(&specs, &bench);
-    csv_row::append_to_csv(csv_path, &row)?;
-
-    let token = google_auth::get_token(client_id, client_secret).await?;
-    google_sheets::append_row(sheet_id, &row, &token).await?;
-    google_drive::upload_csv(drive_folder_id, csv_path, &token).await?;
+async fn run_pipeline(mode: &str, start: RunStart) -> Result<(), MjError> {
+    let want_tui = mode == "tui";
+    let use_tui = want_tui && is_tty_stdout() && !is_ci_env();
+
+    if want_tui && !use_tui {
+        eprintln!("No usable TTY detected — falling back to CLI mode.");
+    }
+
+    if use_tui {
+        tui::app::run_full_pipeline_with_tui(start).await
+    } else {
+        run_full_pipeline_cli(start).await
+    }
+}
+
+async fn run_full_pipeline_cli(start: RunStart) -> Result<(), MjError> {
+    let (handle, mut events, cancel) = pipeline::spawn(start);
+    let mut run_id = String::new();
+    let mut cancelling = false;
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            // The pipeline stops the running step and still sends Finished;
+            // a second Ctrl-C gives up waiting for that.
+            _ = tokio::signal::ctrl_c() => {
+                if cancelling {
+                    // Dropping the task signals the running step's group.
+                    handle.abort();
+                    let _ = handle.await;
+                    eprintln!("Aborted; resume with: mj-benchmark resume {}", run_id);
+                    return Err(MjError::Cancelled);
+                }
+                eprintln!("Cancelling… (Ctrl-C again to abort)");
+                cancel.cancel();
+                cancelling = true;
+                continue;
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        match event {
+            PipelineEvent::RunStarted { run_id: id } => {
+                println!("Run ID: {}", id);
+                run_id = id;
+            }
+            PipelineEvent::StepStarted(step) => println!("==> {}", step.label()),
+            PipelineEvent::StepRestored(step) => {
+                println!("✔ {} (restored from checkpoint)", step.label());
+            }
+            PipelineEvent::Log(msg) => println!("{}", msg),
+            PipelineEvent::StepCompleted(step, elapsed) => {
+                println!("✔ {} ({:.1}s)", step.label(), elapsed.as_secs_f64());
+            }
+            PipelineEvent::StepSkipped(step, reason) => {
+                println!("– {} skipped: {}", step.label(), reason);
+            }
+            PipelineEvent::StepFailed(step, err) => {
+                eprintln!("✖ {}: {}", step.label(), err);
+            }
+            PipelineEvent::StepCancelled(step) => {
+                eprintln!("⊘ {} cancelled", step.label());
+            }
+            // The raw PTS output is already printed as log lines.
+            PipelineEvent::SpecsCollected(_)
+            | PipelineEvent::BenchUpdated(_)
+            | PipelineEvent::PtsProgress(_)
+            | PipelineEvent::Finished(_) => {}
+        }
+    }
+
+    let summary = handle
+        .await
+        .map_err(|e| MjError::Other(format!("pipeline task failed: {}", e)))?;
+
+    println!("Summary:");
+    for (step, outcome) in &summary.outcomes {
+        let line = match outcome {
+            StepOutcome::Completed(d) => format!("✔ {:<22} {:.1}s", step.label(), d.as_secs_f64()),
+            StepOutcome::Restored => format!("✔ {:<22} restored", step.label()),
+            StepOutcome::Skipped(reason) => format!("– {:<22} skipped: {}", step.label(), reason),
+            StepOutcome::Failed(err) => format!("✖ {:<22} FAILED: {}", step.label(), err),
+            StepOutcome::Cancelled => format!("⊘ {:<22} cancelled", step.label()),
+        };
+        println!("  {}", line);
+    }
+
+    if let Some((_, err)) = summary.failure() {
+        eprintln!("Resume with: mj-benchmark resume {}", run_id);
+        return Err(err.clone());
+    }
+    if summary.cancelled().is_some() {
+        eprintln!("Resume with: mj-benchmark resume {}", run_id);
+        return Err(MjError::Cancelled);
+    }
 
     println!("Pipeline complete.");
     Ok(())
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! When, how and by which versions a run was made.
+
+use std::process::Command;
+
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// Same layout as the old schema.csv "Date (YYYY/MM/DD, 24h …)" column,
+/// with the zone spelled out.
+const TIMESTAMP_FORMAT: &str = "%Y/%m/%d %H:%M:%S %Z";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub run_id: String,
+    pub started_at: String,
+    /// Set when measurement ends, just before the CSV row is built.
+    pub finished_at: Option<String>,
+    pub timezone: String,
+    pub mj_version: String,
+    pub pts_version: Option<String>,
+    pub browser_name: Option<String>,
+    pub browser_version: Option<String>,
+    pub driver_version: Option<String>,
+    pub kernel: String,
+    pub hostname: String,
+}
+
+impl RunMetadata {
+    pub fn new(timezone: &str) -> Self {
+        Self {
+            run_id: Uuid::new_v4().to_string(),
+            started_at: now_in(timezone),
+            finished_at: None,
+            timezone: timezone.into(),
+            mj_version: env!("CARGO_PKG_VERSION").into(),
+            pts_version: None,
+            browser_name: None,
+            browser_version: None,
+            driver_version: None,
+            kernel: detect_kernel(),
+            hostname: detect_hostname(),
+        }
+    }
+
+    pub fn mark_finished(&mut self) {
+        self.finished_at = Some(now_in(&self.timezone));
+    }
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>()
+        .map_err(|_| format!("unknown timezone '{}' (expected an IANA name like Asia/Riyadh)", name))
+}
+
+/// Current time in `timezone`, falling back to UTC for unknown names.
+pub fn now_in(timezone: &str) -> String {
+    let tz = parse_timezone(timezone).unwrap_or(Tz::UTC);
+    Utc::now().with_timezone(&tz).format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Reformat a zone-less "YYYY-MM-DD HH:MM:SS" time (as PTS records it)
+/// taken to be in `timezone`.
+pub fn format_local(time: &str, timezone: &str) -> Option<String> {
+    let tz = parse_timezone(timezone).ok()?;
+    let naive = NaiveDateTime::parse_from_str(time.trim(), "%Y-%m-%d %H:%M:%S").ok()?;
+    let local = naive.and_local_timezone(tz).earliest()?;
+    Some(local.format(TIMESTAMP_FORMAT).to_string())
+}
+
+fn run(cmd: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(cmd).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+fn detect_kernel() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/osrelease")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .or_else(|| run("uname", &["-r"]))
+        .or_else(|| run("cmd", &["/C", "ver"]))
+        .unwrap_or_default()
+}
+
+fn detect_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| run("hostname", &[]))
+        .unwrap_or_default()
+}
@@ -9,9 +9,11 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DeviceSpecs {
     pub brand_model: String,
     pub launch_date: String,
@@ -49,7 +51,57 @@ pub struct DeviceSpecs {
     pub bios_boot_key: String,
 }
 
+/// One spec field: serde key, CSV header label, collected value.
+pub type SpecField<'a> = (&'static str, &'static str, &'a str);
+
 impl DeviceSpecs {
+    /// Every field in CSV column order.
+    pub fn fields(&self) -> Vec<SpecField<'_>> {
+        vec![
+            ("brand_model", "Brand & Model", &self.brand_model),
+            ("launch_date", "Launch Date", &self.launch_date),
+            ("price", "Price", &self.price),
+            ("cpu", "CPU & Performance", &self.cpu),
+            ("codename", "Codename", &self.codename),
+            ("cpu_speed", "CPU Speed", &self.cpu_speed),
+            ("x86_level", "x86-64 Level", &self.x86_level),
+            ("gpu", "GPU", &self.gpu),
+            ("ai_npu", "AI & NPU", &self.ai_npu),
+            ("ram_storage", "RAM & Storage", &self.ram_storage),
+            ("connectivity", "Connectivity", &self.connectivity),
+            ("audio_ports", "Audio Ports", &self.audio_ports),
+            ("nfc_wallet", "NFC & Wallet", &self.nfc_wallet),
+            ("battery", "Battery", &self.battery),
+            ("power_charging", "Power & Charging", &self.power_charging),
+            ("qi_charging", "Qi Wireless Charging", &self.qi_charging),
+            ("form_factor", "Form Factor", &self.form_factor),
+            ("dimensions_weight", "Dimensions & Weight", &self.dimensions_weight),
+            ("display", "Display", &self.display),
+            ("build_durability", "Build & Durability", &self.build_durability),
+            ("cameras", "Cameras", &self.cameras),
+            ("biometrics_health", "Biometrics & Health", &self.biometrics_health),
+            ("regional", "Regional", &self.regional),
+            ("software_updates", "Software & Updates", &self.software_updates),
+            ("color", "Color", &self.color),
+            ("upgrade_options", "Upgrade Options", &self.upgrade_options),
+            ("ecosystem_lock_in", "Ecosystem Lock-in", &self.ecosystem_lock_in),
+            ("wear_detection", "Wear Detection", &self.wear_detection),
+            ("touch_control", "Touch Control", &self.touch_control),
+            ("storage_case", "Storage Case", &self.storage_case),
+            ("special_features", "Special Features", &self.special_features),
+            ("official_site", "Official Site", &self.official_site),
+            ("info_links", "Info Links", &self.info_links),
+            ("bios_boot_key", "BIOS/Boot Key", &self.bios_boot_key),
+        ]
+    }
+
+    /// True when a collector came back with nothing useful for a field:
+    /// empty, or one of the "Unknown …" placeholders.
+    pub fn is_unknown(value: &str) -> bool {
+        let v = value.trim();
+        v.is_empty() || v.to_lowercase().starts_with("unknown")
+    }
+
     pub fn dummy() -> Self {
         Self {
             brand_model: "Example Brand Example Model".into(),
@@ -90,7 +142,7 @@ impl DeviceSpecs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BenchResults {
     pub seven_zip_mips: Option<f64>,
     pub openssl_mb_s: Option<f64>,
@@ -99,19 +151,310 @@ pub struct BenchResults {
     pub fio_seq_write_mb_s: Option<f64>,
     pub fio_rand_read_iops: Option<f64>,
     pub fio_rand_write_iops: Option<f64>,
+    /// Option sets the fio columns were taken from.
+    #[serde(default)]
+    pub fio: Option<FioSetup>,
     pub glmark2_score: Option<f64>,
     pub kernel_build_time_s: Option<f64>,
+    /// What produced the PTS columns; `None` when PTS was skipped.
+    #[serde(default)]
+    pub pts_source: Option<PtsSource>,
+    /// Only filled by the native fallback, for what no PTS column
+    /// measures.
+    #[serde(default)]
+    pub native_compress_mb_s: Option<f64>,
+    #[serde(default)]
+    pub native_aes_mb_s: Option<f64>,
+    /// The default suites' scores in the first configured browser; every
+    /// suite in every browser is in `browsers`.
     pub speedometer_score: Option<f64>,
     pub jetstream_score: Option<f64>,
     pub motionmark_score: Option<f64>,
+    /// Which copy of each suite produced the scores, e.g. "2.1
+    /// (browserbench.org)" or the local copy's VERSION.
+    #[serde(default)]
+    pub speedometer_version: Option<String>,
+    #[serde(default)]
+    pub jetstream_version: Option<String>,
+    #[serde(default)]
+    pub motionmark_version: Option<String>,
+    /// One record per `browsers` entry in the config, in config order.
+    #[serde(default)]
+    pub browsers: Vec<BrowserScores>,
     pub battery_full_wh: Option<f64>,
     pub battery_design_wh: Option<f64>,
     pub battery_health_percent: Option<f64>,
     pub battery_cycle_count: Option<u32>,
     pub notes: String,
+    /// Columns defined by `pts_columns` in the config, in config order.
+    #[serde(default)]
+    pub custom: Vec<CustomMetric>,
+    /// Per-trial statistics for PTS metrics, by metric key (custom
+    /// columns by label).
+    #[serde(default)]
+    pub stats: BTreeMap<String, MetricStats>,
+}
+
+/// Spread of one PTS metric over its trials, in the metric's unit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricStats {
+    pub trials: usize,
+    pub mean: f64,
+    /// Sample standard deviation; 0 for a single trial.
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub raw: Vec<f64>,
+}
+
+impl MetricStats {
+    pub fn from_samples(raw: Vec<f64>) -> Option<Self> {
+        if raw.is_empty() {
+            return None;
+        }
+        let n = raw.len() as f64;
+        let mean = raw.iter().sum::<f64>() / n;
+        let std_dev = if raw.len() > 1 {
+            (raw.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Some(Self {
+            trials: raw.len(),
+            mean,
+            std_dev,
+            min: raw.iter().copied().fold(f64::INFINITY, f64::min),
+            max: raw.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            raw,
+        })
+    }
+}
+
+/// Whether the PTS columns hold Phoronix Test Suite results or the
+/// built-in fallback's (`native_bench`), which are not comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PtsSource {
+    Pts,
+    Native,
+}
+
+impl PtsSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PtsSource::Pts => "pts",
+            PtsSource::Native => "native",
+        }
+    }
+}
+
+/// Block sizes and target behind the fio columns, as PTS reports them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FioSetup {
+    pub seq_block_size: Option<String>,
+    pub rand_block_size: Option<String>,
+    /// fio's "Disk Target": a mount point, or PTS's default test directory.
+    pub test_path: Option<String>,
+}
+
+/// A user-defined PTS column; see `pts_results::PtsColumn`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomMetric {
+    pub label: String,
+    pub unit: String,
+    pub value: Option<f64>,
+}
+
+/// Browser suite scores from one browser, tagged with what ran them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BrowserScores {
+    /// The config entry's `name`, used in CSV column labels.
+    pub name: String,
+    /// As the browser reports itself, e.g. "Firefox" and "128.0".
+    pub browser_name: Option<String>,
+    pub browser_version: Option<String>,
+    /// "Blink", "Gecko" or "WebKit".
+    pub engine: Option<String>,
+    pub driver_version: Option<String>,
+    /// One per suite run, in run order.
+    #[serde(default)]
+    pub suites: Vec<SuiteScore>,
+    /// Why the browser has no scores, when its session failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One browser suite's result in one browser.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SuiteScore {
+    /// The suite's `key`; its metric key is `<key>_score`.
+    pub key: String,
+    /// E.g. "Speedometer 3.1"; columns are "<name> Score".
+    pub name: String,
+    pub unit: String,
+    /// The suite copy that was run, e.g. "3.1 (browserbench.org)".
+    pub version: Option<String>,
+    pub score: Option<f64>,
+    /// By name, for suites that define how to read them.
+    #[serde(default)]
+    pub subscores: BTreeMap<String, f64>,
+    /// Why there is no score, when the suite failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One benchmark value with its CSV label and unit.
+#[derive(Debug, Clone, Serialize)]
+pub struct Metric {
+    pub key: String,
+    pub label: String,
+    pub unit: String,
+    pub value: Option<f64>,
+    pub stats: Option<MetricStats>,
+    /// Set on PTS-section metrics: "pts" or "native".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PtsSource>,
+    /// Set on browser suite metrics: the suite copy that was run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 impl BenchResults {
+    /// Keys of the built-in columns filled from PTS.
+    pub const PTS_KEYS: [&'static str; 9] = [
+        "seven_zip_mips",
+        "openssl_mb_s",
+        "ramspeed_mb_s",
+        "fio_seq_read_mb_s",
+        "fio_seq_write_mb_s",
+        "fio_rand_read_iops",
+        "fio_rand_write_iops",
+        "glmark2_score",
+        "kernel_build_time_s",
+    ];
+
+    /// Suite keys with a fixed CSV column, run when a profile sets no
+    /// `browser_tests`; other suites get columns after the custom ones.
+    pub const FIXED_SUITE_KEYS: [&'static str; 3] = ["speedometer", "jetstream", "motionmark"];
+
+    /// Store the browser step's records, filling the fixed suite columns
+    /// from the first browser.
+    pub fn set_browsers(&mut self, browsers: Vec<BrowserScores>) {
+        let first = browsers.first();
+        let suite = |key: &str| first.and_then(|b| b.suites.iter().find(|s| s.key == key));
+        let score = |key| suite(key).and_then(|s| s.score);
+        let version = |key| suite(key).and_then(|s| s.version.clone());
+        self.speedometer_score = score("speedometer");
+        self.jetstream_score = score("jetstream");
+        self.motionmark_score = score("motionmark");
+        self.speedometer_version = version("speedometer");
+        self.jetstream_version = version("jetstream");
+        self.motionmark_version = version("motionmark");
+        self.browsers = browsers;
+    }
+
+    /// The first browser's suites that have no fixed column.
+    pub fn extra_suites(&self) -> impl Iterator<Item = &SuiteScore> {
+        self.browsers
+            .first()
+            .into_iter()
+            .flat_map(|b| &b.suites)
+            .filter(|s| !Self::FIXED_SUITE_KEYS.contains(&s.key.as_str()))
+    }
+
+    /// Every benchmark value in CSV column order: fixed columns, custom
+    /// PTS columns, then suites without a fixed column.
+    pub fn metrics(&self) -> Vec<Metric> {
+        let m = |key: &str, label: &str, unit: &str, value| Metric {
+            key: key.into(),
+            label: label.into(),
+            unit: unit.into(),
+            value,
+            stats: self.stats.get(key).cloned(),
+            source: None,
+            version: None,
+        };
+        let mut metrics = vec![
+            m("seven_zip_mips", "7-Zip MIPS", "MIPS", self.seven_zip_mips),
+            m("openssl_mb_s", "OpenSSL MB/s", "MB/s", self.openssl_mb_s),
+            m("ramspeed_mb_s", "RAMspeed MB/s", "MB/s", self.ramspeed_mb_s),
+            m("fio_seq_read_mb_s", "fio Seq Read MB/s", "MB/s", self.fio_seq_read_mb_s),
+            m("fio_seq_write_mb_s", "fio Seq Write MB/s", "MB/s", self.fio_seq_write_mb_s),
+            m("fio_rand_read_iops", "fio Rand Read IOPS", "IOPS", self.fio_rand_read_iops),
+            m("fio_rand_write_iops", "fio Rand Write IOPS", "IOPS", self.fio_rand_write_iops),
+            m("glmark2_score", "glmark2 Score", "score", self.glmark2_score),
+            m("kernel_build_time_s", "Kernel Build Time (s)", "s", self.kernel_build_time_s),
+            m("speedometer_score", "Speedometer 2.1 Score", "runs/min", self.speedometer_score),
+            m("jetstream_score", "JetStream 2.2 Score", "score", self.jetstream_score),
+            m("motionmark_score", "MotionMark 1.3 Score", "score", self.motionmark_score),
+            m("battery_full_wh", "Battery Full Capacity (Wh)", "Wh", self.battery_full_wh),
+            m("battery_design_wh", "Battery Design Capacity (Wh)", "Wh", self.battery_design_wh),
+            m("battery_health_percent", "Battery Health (%)", "%", self.battery_health_percent),
+            m(
+                "battery_cycle_count",
+                "Battery Cycle Count",
+                "cycles",
+                self.battery_cycle_count.map(f64::from),
+            ),
+            m("native_compress_mb_s", "Native Compression MB/s", "MB/s", self.native_compress_mb_s),
+            m("native_aes_mb_s", "Native AES-256 MB/s", "MB/s", self.native_aes_mb_s),
+        ];
+        metrics.extend(
+            self.custom
+                .iter()
+                .map(|c| m(&c.label, &c.label, &c.unit, c.value)),
+        );
+        for metric in &mut metrics {
+            if Self::PTS_KEYS.contains(&metric.key.as_str())
+                || metric.key.starts_with("native_")
+                || self.custom.iter().any(|c| c.label == metric.key)
+            {
+                metric.source = self.pts_source;
+            }
+            metric.version = match metric.key.as_str() {
+                "speedometer_score" => self.speedometer_version.clone(),
+                "jetstream_score" => self.jetstream_version.clone(),
+                "motionmark_score" => self.motionmark_version.clone(),
+                _ => None,
+            };
+        }
+        for suite in self.extra_suites() {
+            let key = format!("{}_score", suite.key);
+            metrics.push(Metric {
+                version: suite.version.clone(),
+                ..m(&key, &format!("{} Score", suite.name), &suite.unit, suite.score)
+            });
+        }
+        metrics
+    }
+
+    /// The metrics that come from PTS: built-in PTS columns, then custom.
+    pub fn pts_metrics(&self) -> Vec<Metric> {
+        self.metrics()
+            .into_iter()
+            .filter(|m| {
+                Self::PTS_KEYS.contains(&m.key.as_str()) || self.custom.iter().any(|c| c.label == m.key)
+            })
+            .collect()
+    }
+
+    /// The built-in PTS column with metric key `key`, for `pts_columns`
+    /// entries that re-map it.
+    pub fn pts_metric_mut(&mut self, key: &str) -> Option<&mut Option<f64>> {
+        match key {
+            "seven_zip_mips" => Some(&mut self.seven_zip_mips),
+            "openssl_mb_s" => Some(&mut self.openssl_mb_s),
+            "ramspeed_mb_s" => Some(&mut self.ramspeed_mb_s),
+            "fio_seq_read_mb_s" => Some(&mut self.fio_seq_read_mb_s),
+            "fio_seq_write_mb_s" => Some(&mut self.fio_seq_write_mb_s),
+            "fio_rand_read_iops" => Some(&mut self.fio_rand_read_iops),
+            "fio_rand_write_iops" => Some(&mut self.fio_rand_write_iops),
+            "glmark2_score" => Some(&mut self.glmark2_score),
+            "kernel_build_time_s" => Some(&mut self.kernel_build_time_s),
+            _ => None,
+        }
+    }
+
     pub fn dummy() -> Self {
         Self {
             seven_zip_mips: Some(25000.0),
@@ -121,16 +464,26 @@ impl BenchResults {
             fio_seq_write_mb_s: Some(700.0),
             fio_rand_read_iops: Some(50000.0),
             fio_rand_write_iops: Some(45000.0),
+            fio: None,
             glmark2_score: Some(1500.0),
             kernel_build_time_s: Some(900.0),
+            pts_source: Some(PtsSource::Pts),
+            native_compress_mb_s: None,
+            native_aes_mb_s: None,
             speedometer_score: Some(120.0),
             jetstream_score: Some(200.0),
             motionmark_score: Some(300.0),
+            speedometer_version: None,
+            jetstream_version: None,
+            motionmark_version: None,
+            browsers: Vec::new(),
             battery_full_wh: Some(48.0),
             battery_design_wh: Some(50.0),
             battery_health_percent: Some(96.0),
             battery_cycle_count: Some(150),
             notes: "Dummy benchmark run (Rust prototype)".into(),
+            custom: Vec::new(),
+            stats: BTreeMap::new(),
         }
     }
 }
\ No newline at end of file
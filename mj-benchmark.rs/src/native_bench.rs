@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Built-in micro-benchmarks for machines without the Phoronix Test Suite
+//! (Windows, BSD, locked-down hosts).
+//!
+//! They fill the PTS columns they have a counterpart for (SHA-256 as
+//! OpenSSL, memory copy as RAMspeed, file write/read as fio sequential)
+//! and their own `native_*` columns for the rest, and mark the row's
+//! `pts_source` as native. They are not comparable with PTS numbers: the
+//! file test goes through the page cache, and the algorithms are plain
+//! Rust rather than the tuned ones PTS builds.
+
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::model::{BenchResults, FioSetup, MetricStats, PtsSource};
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+/// Runs per measurement; each becomes one trial in the stats.
+pub const TRIALS: usize = 3;
+
+const MB: f64 = 1e6;
+/// Test data generated once; every measurement reads from it.
+const DATA_BYTES: usize = 64 << 20;
+/// SHA-256 and AES stream this much, `CHUNK_BYTES` at a time.
+const HASH_BYTES: usize = 256 << 20;
+const CHUNK_BYTES: usize = 8 << 20;
+const COMPRESS_BYTES: usize = 32 << 20;
+const COPY_BYTES: usize = DATA_BYTES;
+const COPY_ROUNDS: usize = 8;
+const FILE_BYTES: usize = 256 << 20;
+const FILE_BLOCK: usize = 1 << 20;
+pub const FILE_BLOCK_SIZE: &str = "1MB";
+
+#[derive(Debug, Clone, Error)]
+pub enum NativeError {
+    #[error("file test in {0}: {1}")]
+    Io(String, String),
+}
+
+/// MB/s per trial for each measurement.
+#[derive(Debug, Clone)]
+pub struct NativeResults {
+    pub compress: Vec<f64>,
+    pub sha256: Vec<f64>,
+    pub aes: Vec<f64>,
+    pub memcpy: Vec<f64>,
+    pub file_write: Vec<f64>,
+    pub file_read: Vec<f64>,
+    pub test_path: PathBuf,
+}
+
+/// Run every measurement, writing the file test's scratch file to `dir`.
+pub fn run(dir: &Path, mut on_line: impl FnMut(&str)) -> Result<NativeResults, NativeError> {
+    let data = test_data(DATA_BYTES);
+    let chunk = &data[..CHUNK_BYTES];
+
+    on_line("native: deflate compression…");
+    let compress = trials(|| compress_mb_s(&data[..COMPRESS_BYTES]));
+    on_line("native: SHA-256…");
+    let sha256 = trials(|| sha256_mb_s(chunk, HASH_BYTES / CHUNK_BYTES));
+    on_line("native: AES-256-CTR…");
+    let aes = trials(|| aes_mb_s(chunk, HASH_BYTES / CHUNK_BYTES));
+    on_line("native: memory copy…");
+    let memcpy = trials(|| memcpy_mb_s(&data[..COPY_BYTES]));
+
+    on_line(&format!("native: sequential file write/read in {}…", dir.display()));
+    let path = dir.join(format!("mj-bench-native-{}.tmp", std::process::id()));
+    let file = file_mb_s(&path, &data[..FILE_BLOCK]);
+    let _ = fs::remove_file(&path);
+    let (file_write, file_read) =
+        file.map_err(|e| NativeError::Io(dir.display().to_string(), e.to_string()))?;
+
+    Ok(NativeResults {
+        compress,
+        sha256,
+        aes,
+        memcpy,
+        file_write,
+        file_read,
+        test_path: dir.to_path_buf(),
+    })
+}
+
+/// Fill the columns of `bench` from `native` and tag it as native. Each
+/// column gets the mean of its trials, as PTS reports the average.
+pub fn apply(bench: &mut BenchResults, native: NativeResults) {
+    let columns = [
+        ("openssl_mb_s", native.sha256),
+        ("ramspeed_mb_s", native.memcpy),
+        ("fio_seq_read_mb_s", native.file_read),
+        ("fio_seq_write_mb_s", native.file_write),
+        ("native_compress_mb_s", native.compress),
+        ("native_aes_mb_s", native.aes),
+    ];
+    for (key, samples) in columns {
+        let Some(stats) = MetricStats::from_samples(samples) else {
+            continue;
+        };
+        let slot = match key {
+            "native_compress_mb_s" => Some(&mut bench.native_compress_mb_s),
+            "native_aes_mb_s" => Some(&mut bench.native_aes_mb_s),
+            _ => bench.pts_metric_mut(key),
+        };
+        if let Some(slot) = slot {
+            *slot = Some(stats.mean);
+        }
+        bench.stats.insert(key.to_string(), stats);
+    }
+
+    bench.fio = Some(FioSetup {
+        seq_block_size: Some(FILE_BLOCK_SIZE.into()),
+        rand_block_size: None,
+        test_path: Some(native.test_path.display().to_string()),
+    });
+    bench.pts_source = Some(PtsSource::Native);
+    bench.notes = format!(
+        "Native micro-benchmarks (phoronix-test-suite not available; file test in {} with {} blocks); not comparable with PTS",
+        native.test_path.display(),
+        FILE_BLOCK_SIZE
+    );
+}
+
+fn trials(mut measure: impl FnMut() -> f64) -> Vec<f64> {
+    (0..TRIALS).map(|_| measure()).collect()
+}
+
+fn mb_s(bytes: usize, start: Instant) -> f64 {
+    bytes as f64 / MB / start.elapsed().as_secs_f64().max(1e-9)
+}
+
+/// Text-like bytes (a small alphabet with runs) so deflate has something
+/// to find, unlike random data.
+fn test_data(len: usize) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"etaoinshrdlu cmfwypvbgkqjxz,.\n";
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ALPHABET[(state % ALPHABET.len() as u64) as usize]
+        })
+        .collect()
+}
+
+fn compress_mb_s(data: &[u8]) -> f64 {
+    let start = Instant::now();
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    // Writing to a Vec cannot fail.
+    let _ = encoder.write_all(data);
+    black_box(encoder.finish().map(|out| out.len()).unwrap_or_default());
+    mb_s(data.len(), start)
+}
+
+/// Hash `chunk` `rounds` times as one stream.
+fn sha256_mb_s(chunk: &[u8], rounds: usize) -> f64 {
+    let start = Instant::now();
+    let mut hasher = Sha256::new();
+    for _ in 0..rounds {
+        hasher.update(black_box(chunk));
+    }
+    black_box(hasher.finalize());
+    mb_s(chunk.len() * rounds, start)
+}
+
+/// Encrypt a copy of `chunk` in place `rounds` times with one running
+/// keystream.
+fn aes_mb_s(chunk: &[u8], rounds: usize) -> f64 {
+    let mut buf = chunk.to_vec();
+    let start = Instant::now();
+    let mut cipher = Aes256Ctr::new(&[7u8; 32].into(), &[9u8; 16].into());
+    for _ in 0..rounds {
+        cipher.apply_keystream(&mut buf);
+        black_box(&buf);
+    }
+    mb_s(buf.len() * rounds, start)
+}
+
+fn memcpy_mb_s(data: &[u8]) -> f64 {
+    let mut dst = vec![0u8; data.len()];
+    let start = Instant::now();
+    for _ in 0..COPY_ROUNDS {
+        dst.copy_from_slice(black_box(data));
+        black_box(&dst);
+    }
+    mb_s(data.len() * COPY_ROUNDS, start)
+}
+
+/// Write then read `FILE_BYTES` in `block`-sized chunks, `TRIALS` times.
+/// Reads are buffered, so they largely measure the page cache.
+fn file_mb_s(path: &Path, block: &[u8]) -> std::io::Result<(Vec<f64>, Vec<f64>)> {
+    let mut writes = Vec::with_capacity(TRIALS);
+    let mut reads = Vec::with_capacity(TRIALS);
+    let mut buf = vec![0u8; block.len()];
+
+    for _ in 0..TRIALS {
+        let start = Instant::now();
+        let mut file = File::create(path)?;
+        for _ in 0..FILE_BYTES / block.len() {
+            file.write_all(block)?;
+        }
+        file.sync_all()?;
+        writes.push(mb_s(FILE_BYTES, start));
+
+        let start = Instant::now();
+        let mut file = File::open(path)?;
+        let mut total = 0;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            total += n;
+        }
+        reads.push(mb_s(total, start));
+    }
+
+    Ok((writes, reads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> NativeResults {
+        NativeResults {
+            compress: vec![100.0, 110.0],
+            sha256: vec![500.0],
+            aes: vec![900.0, 1100.0],
+            memcpy: vec![8000.0],
+            file_write: vec![1000.0],
+            file_read: vec![3000.0],
+            test_path: PathBuf::from("/tmp"),
+        }
+    }
+
+    #[test]
+    fn apply_fills_the_matching_pts_columns() {
+        let mut bench = BenchResults::default();
+        apply(&mut bench, sample());
+
+        assert_eq!(bench.openssl_mb_s, Some(500.0));
+        assert_eq!(bench.ramspeed_mb_s, Some(8000.0));
+        assert_eq!(bench.fio_seq_write_mb_s, Some(1000.0));
+        assert_eq!(bench.fio_seq_read_mb_s, Some(3000.0));
+        assert_eq!(bench.native_compress_mb_s, Some(105.0));
+        assert_eq!(bench.native_aes_mb_s, Some(1000.0));
+        assert_eq!(bench.stats["native_aes_mb_s"].trials, 2);
+
+        assert_eq!(bench.seven_zip_mips, None);
+        assert_eq!(bench.fio_rand_read_iops, None);
+        let fio = bench.fio.as_ref().unwrap();
+        assert_eq!(fio.seq_block_size.as_deref(), Some(FILE_BLOCK_SIZE));
+        assert_eq!(fio.test_path.as_deref(), Some("/tmp"));
+        assert_eq!(bench.pts_source, Some(PtsSource::Native));
+        assert!(bench.notes.contains("/tmp"), "{}", bench.notes);
+    }
+
+    #[test]
+    fn native_values_are_labeled_native() {
+        let mut bench = BenchResults::default();
+        apply(&mut bench, sample());
+        let metrics = bench.metrics();
+        let metric = |key: &str| metrics.iter().find(|m| m.key == key).unwrap();
+
+        let openssl = metric("openssl_mb_s");
+        assert_eq!(openssl.value, Some(500.0));
+        assert_eq!(openssl.source, Some(PtsSource::Native));
+        assert_eq!(metric("native_aes_mb_s").source, Some(PtsSource::Native));
+        assert_eq!(bench.pts_metrics().iter().filter(|m| m.value.is_some()).count(), 4);
+
+        let row = crate::csv_row::build_csv_row(
+            &crate::model::DeviceSpecs::default(),
+            &bench,
+            &crate::metadata::RunMetadata::new("UTC"),
+            false,
+        );
+        let header = crate::csv_row::header(&bench, false);
+        let cell = |label: &str| {
+            let at = header.split(',').position(|h| h == label).unwrap();
+            row.split(',').nth(at).unwrap().to_string()
+        };
+        assert_eq!(cell("OpenSSL MB/s"), "500");
+        assert_eq!(cell("PTS Source"), "native");
+    }
+
+    #[test]
+    fn empty_trials_leave_the_column_empty() {
+        let mut bench = BenchResults::default();
+        apply(&mut bench, NativeResults { file_read: Vec::new(), ..sample() });
+        assert_eq!(bench.fio_seq_read_mb_s, None);
+        assert!(!bench.stats.contains_key("fio_seq_read_mb_s"));
+    }
+}
@@ -0,0 +1,1080 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Shared pipeline engine.
+//!
+//! The step sequence (specs → PTS → browser → CSV → Sheets → Drive) is
+//! defined once here. Front ends (CLI printer, TUI) only consume the
+//! [`PipelineEvent`] stream and never drive the steps themselves.
+
+use std::str::FromStr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::browser_bench::{BrowserError, BrowserSuite, BrowserTarget};
+use crate::checkpoint::Checkpoint;
+use crate::config::ConfigError;
+use crate::error::MjError;
+use crate::google_auth::{GoogleAuthError, StoredToken};
+use crate::model::{BenchResults, BrowserScores, DeviceSpecs, SuiteScore};
+use crate::metadata::{self, RunMetadata};
+use crate::platform::{self, Platform};
+use crate::pts_progress::PtsProgress;
+use crate::pts_results::{self, PtsColumn};
+use crate::report::RunReport;
+use crate::suite_server::{self, SuiteServer};
+use crate::webdriver::{self, ManagedDriver, WebDriverMode};
+use crate::{
+    browser_bench, collect_specs, csv_row, google_auth, google_drive, google_sheets, native_bench,
+    pts, pts_batch, sync,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStep {
+    Specs,
+    Pts,
+    Browser,
+    Csv,
+    Sheets,
+    Drive,
+    Done,
+}
+
+impl PipelineStep {
+    /// Every runnable step, in execution order.
+    pub const ALL: [PipelineStep; 6] = [
+        PipelineStep::Specs,
+        PipelineStep::Pts,
+        PipelineStep::Browser,
+        PipelineStep::Csv,
+        PipelineStep::Sheets,
+        PipelineStep::Drive,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PipelineStep::Specs => "Specs",
+            PipelineStep::Pts => "PTS Benchmarks",
+            PipelineStep::Browser => "Browser Benchmarks",
+            PipelineStep::Csv => "CSV Build",
+            PipelineStep::Sheets => "Google Sheets Sync",
+            PipelineStep::Drive => "Google Drive Upload",
+            PipelineStep::Done => "Done",
+        }
+    }
+
+    /// Steps that produce data; `--only` selects among these. Output steps
+    /// (CSV, Sheets, Drive) always run unless named in `--skip`.
+    pub fn is_measurement(self) -> bool {
+        matches!(self, PipelineStep::Specs | PipelineStep::Pts | PipelineStep::Browser)
+    }
+}
+
+impl FromStr for PipelineStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "specs" => Ok(PipelineStep::Specs),
+            "pts" => Ok(PipelineStep::Pts),
+            "browser" => Ok(PipelineStep::Browser),
+            "csv" => Ok(PipelineStep::Csv),
+            "sheets" => Ok(PipelineStep::Sheets),
+            "drive" => Ok(PipelineStep::Drive),
+            other => Err(format!(
+                "unknown step '{}' (expected specs, pts, browser, csv, sheets or drive)",
+                other
+            )),
+        }
+    }
+}
+
+/// Limits in seconds; unset means no limit. A step over its limit fails
+/// with [`MjError::Timeout`] and can be resumed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Timeouts {
+    pub specs: Option<u64>,
+    pub pts: Option<u64>,
+    pub browser: Option<u64>,
+    pub csv: Option<u64>,
+    pub sheets: Option<u64>,
+    pub drive: Option<u64>,
+    /// Per PTS test (all runs of one option set), not per suite.
+    pub pts_test: Option<u64>,
+    /// Per browser suite; `browser_bench::DEFAULT_SUITE_TIMEOUT` if unset.
+    pub browser_suite: Option<u64>,
+}
+
+impl Timeouts {
+    pub fn step(&self, step: PipelineStep) -> Option<Duration> {
+        let secs = match step {
+            PipelineStep::Specs => self.specs,
+            PipelineStep::Pts => self.pts,
+            PipelineStep::Browser => self.browser,
+            PipelineStep::Csv => self.csv,
+            PipelineStep::Sheets => self.sheets,
+            PipelineStep::Drive => self.drive,
+            PipelineStep::Done => None,
+        };
+        secs.map(Duration::from_secs)
+    }
+
+    pub fn pts_test(&self) -> Option<Duration> {
+        self.pts_test.map(Duration::from_secs)
+    }
+
+    pub fn browser_suite(&self) -> Option<Duration> {
+        self.browser_suite.map(Duration::from_secs)
+    }
+
+    /// Config key and value of every limit.
+    pub fn entries(&self) -> [(&'static str, Option<u64>); 8] {
+        [
+            ("specs", self.specs),
+            ("pts", self.pts),
+            ("pts_test", self.pts_test),
+            ("browser", self.browser),
+            ("browser_suite", self.browser_suite),
+            ("csv", self.csv),
+            ("sheets", self.sheets),
+            ("drive", self.drive),
+        ]
+    }
+
+    /// `over`'s limits, falling back to `self`'s for those it leaves unset.
+    pub fn merge(self, over: Timeouts) -> Timeouts {
+        Timeouts {
+            specs: over.specs.or(self.specs),
+            pts: over.pts.or(self.pts),
+            browser: over.browser.or(self.browser),
+            csv: over.csv.or(self.csv),
+            sheets: over.sheets.or(self.sheets),
+            drive: over.drive.or(self.drive),
+            pts_test: over.pts_test.or(self.pts_test),
+            browser_suite: over.browser_suite.or(self.browser_suite),
+        }
+    }
+
+    /// "pts=7200s, pts_test=1800s", or "none".
+    pub fn describe(&self) -> String {
+        let set: Vec<String> = self
+            .entries()
+            .into_iter()
+            .filter_map(|(name, secs)| Some(format!("{}={}s", name, secs?)))
+            .collect();
+        if set.is_empty() {
+            "none".into()
+        } else {
+            set.join(", ")
+        }
+    }
+}
+
+/// Cloud targets are optional: a step whose target or credentials are
+/// missing is skipped (with a reason) instead of failing the run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    pub sheet_id: Option<String>,
+    pub drive_folder_id: Option<String>,
+    pub csv_path: String,
+    pub client_id: Option<String>,
+    /// Never written to the checkpoint; resume looks it up again.
+    #[serde(skip)]
+    pub client_secret: Option<String>,
+    /// Config profile the run was resolved from.
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub local_only: bool,
+    #[serde(default = "default_pts_suite")]
+    pub pts_suite: String,
+    pub pts_tests: Vec<String>,
+    #[serde(default)]
+    pub pts_columns: Vec<PtsColumn>,
+    /// Run `native_bench` when phoronix-test-suite is missing.
+    #[serde(default = "default_native_fallback")]
+    pub native_fallback: bool,
+    /// Patch PTS's batch settings for the run, then restore the file.
+    #[serde(default = "default_pts_batch_setup")]
+    pub pts_batch_setup: bool,
+    pub webdriver_url: String,
+    /// Connect to `webdriver_url`, or start a driver for the step.
+    #[serde(default)]
+    pub webdriver: WebDriverMode,
+    /// Browsers to run the suites in; empty means one run against
+    /// `webdriver`/`webdriver_url` with default capabilities.
+    #[serde(default)]
+    pub browsers: Vec<BrowserTarget>,
+    /// Serve the suites from this local copy instead of browserbench.org.
+    #[serde(default)]
+    pub browser_suites: Option<String>,
+    /// Keys of the suites to run; `browser_bench::select_suites` decides
+    /// when unset.
+    #[serde(default)]
+    pub browser_tests: Option<Vec<String>>,
+    /// Suites added to the built-ins, or replacing one by key.
+    #[serde(default)]
+    pub browser_suite_defs: Vec<BrowserSuite>,
+    /// Add trial count, mean, std dev, min and max columns per PTS metric.
+    #[serde(default)]
+    pub csv_stats: bool,
+    #[serde(default)]
+    pub only: Option<Vec<PipelineStep>>,
+    #[serde(default)]
+    pub skip: Vec<PipelineStep>,
+    #[serde(default)]
+    pub output_json: Option<String>,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+}
+
+fn default_timezone() -> String {
+    metadata::DEFAULT_TIMEZONE.into()
+}
+
+fn default_native_fallback() -> bool {
+    true
+}
+
+fn default_pts_batch_setup() -> bool {
+    true
+}
+
+fn default_pts_suite() -> String {
+    pts::SUITE_NAME.into()
+}
+
+impl PipelineConfig {
+    pub fn suite(&self) -> pts::Suite {
+        pts::Suite {
+            name: self.pts_suite.clone(),
+            tests: self.pts_tests.clone(),
+            columns: pts_results::effective_columns(&self.pts_columns),
+        }
+    }
+
+    /// The configured browsers, or the single profile-default one.
+    pub fn browser_targets(&self) -> Vec<BrowserTarget> {
+        if self.browsers.is_empty() {
+            vec![BrowserTarget::profile_default()]
+        } else {
+            self.browsers.clone()
+        }
+    }
+
+    /// Driver mode and endpoint for `target`, falling back to the profile's.
+    pub fn driver_for<'a>(&'a self, target: &'a BrowserTarget) -> (WebDriverMode, &'a str) {
+        (
+            target.webdriver.unwrap_or(self.webdriver),
+            target.webdriver_url.as_deref().unwrap_or(&self.webdriver_url),
+        )
+    }
+
+    /// The browser suites to run, in order.
+    pub fn browser_suite_list(&self) -> Result<Vec<BrowserSuite>, ConfigError> {
+        browser_bench::select_suites(self.browser_tests.as_deref(), &self.browser_suite_defs)
+            .map_err(ConfigError::Invalid)
+    }
+
+    /// Which copy of `suite` a run uses.
+    pub fn suite_version(&self, suite: &BrowserSuite) -> String {
+        match &self.browser_suites {
+            Some(dir) if !suite.is_absolute() => suite_server::suite_version(Path::new(dir), suite),
+            _ if suite.is_absolute() => format!("{} ({})", suite.version, suite.path),
+            _ => format!("{} (browserbench.org)", suite.version),
+        }
+    }
+
+    /// A record per browser and suite with no scores, so rows without
+    /// browser results still get the suite and per-browser columns.
+    pub fn empty_browser_scores(&self) -> Vec<BrowserScores> {
+        let suites = empty_suite_scores(&self.browser_suite_list().unwrap_or_default());
+        self.browser_targets()
+            .into_iter()
+            .map(|b| BrowserScores {
+                name: b.name,
+                suites: suites.clone(),
+                ..BrowserScores::default()
+            })
+            .collect()
+    }
+
+    /// `bench` with empty custom and browser columns where the PTS or
+    /// browser step left none, so every row has this config's columns.
+    pub fn with_empty_columns(&self, mut bench: BenchResults) -> BenchResults {
+        if bench.custom.is_empty() {
+            bench.custom = pts_results::empty_custom_metrics(&self.suite().columns);
+        }
+        if bench.browsers.is_empty() {
+            bench.browsers = self.empty_browser_scores();
+        }
+        bench
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.client_id.is_some() && self.client_secret.is_some()
+    }
+
+    /// Why `step` will not run with this configuration, if it won't.
+    pub fn skip_reason(&self, step: PipelineStep) -> Option<String> {
+        if self.skip.contains(&step) {
+            return Some("skipped by --skip".into());
+        }
+        if let Some(only) = &self.only {
+            if step.is_measurement() && !only.contains(&step) {
+                return Some("not selected by --only".into());
+            }
+        }
+
+        let (target, flag) = match step {
+            PipelineStep::Sheets => (&self.sheet_id, "--sheet-id"),
+            PipelineStep::Drive => (&self.drive_folder_id, "--drive-folder-id"),
+            _ => return None,
+        };
+
+        if self.local_only {
+            Some("local-only run".into())
+        } else if target.is_none() {
+            Some(format!("no {} given", flag))
+        } else if !self.has_credentials() {
+            Some("no Google client credentials (--client-id / --client-secret)".into())
+        } else {
+            None
+        }
+    }
+}
+
+/// A record per suite with no score.
+fn empty_suite_scores(suites: &[BrowserSuite]) -> Vec<SuiteScore> {
+    suites
+        .iter()
+        .map(|s| SuiteScore {
+            key: s.key.clone(),
+            name: s.name.clone(),
+            unit: s.unit.clone(),
+            ..SuiteScore::default()
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub enum StepOutcome {
+    Completed(Duration),
+    /// Finished in an earlier attempt and restored from the checkpoint.
+    Restored,
+    Skipped(String),
+    Failed(MjError),
+    /// Stopped by the user; resumable like a failure.
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    pub metadata: RunMetadata,
+    pub outcomes: Vec<(PipelineStep, StepOutcome)>,
+    pub specs: Option<DeviceSpecs>,
+    pub bench: Option<BenchResults>,
+}
+
+impl RunSummary {
+    pub fn failure(&self) -> Option<(PipelineStep, &MjError)> {
+        self.outcomes.iter().find_map(|(step, outcome)| match outcome {
+            StepOutcome::Failed(err) => Some((*step, err)),
+            _ => None,
+        })
+    }
+
+    pub fn cancelled(&self) -> Option<PipelineStep> {
+        self.outcomes.iter().find_map(|(step, outcome)| match outcome {
+            StepOutcome::Cancelled => Some(*step),
+            _ => None,
+        })
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.failure().is_none() && self.cancelled().is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PipelineEvent {
+    RunStarted { run_id: String },
+    StepStarted(PipelineStep),
+    StepRestored(PipelineStep),
+    Log(String),
+    SpecsCollected(Box<DeviceSpecs>),
+    BenchUpdated(Box<BenchResults>),
+    /// Parsed from the PTS output while the suite runs.
+    PtsProgress(PtsProgress),
+    StepCompleted(PipelineStep, Duration),
+    StepSkipped(PipelineStep, String),
+    StepFailed(PipelineStep, MjError),
+    StepCancelled(PipelineStep),
+    Finished(Box<RunSummary>),
+}
+
+/// Print what a run with `config` would do, step by step, without
+/// running anything. Mirrors `install::print_install_plan`.
+pub fn print_plan(config: &PipelineConfig) -> Result<(), MjError> {
+    let platform = platform::detect_platform();
+    println!("Detected platform: {}", platform);
+
+    println!("Timeouts: {}", config.timeouts.describe());
+
+    for step in PipelineStep::ALL {
+        println!();
+        if let Some(reason) = config.skip_reason(step) {
+            println!("[{}] skipped: {}", step.label(), reason);
+            continue;
+        }
+
+        println!("[{}]", step.label());
+        match step {
+            PipelineStep::Specs => {
+                println!("  collect device specs with the {} collector", platform);
+            }
+            PipelineStep::Pts => {
+                let suite = config.suite();
+                for cmd in pts::planned_commands(&suite) {
+                    println!("  {}", cmd);
+                }
+                let in_suite = |test: &str| {
+                    let test = pts_results::base_identifier(test);
+                    suite.tests.iter().any(|t| pts_results::base_identifier(t) == test)
+                };
+                for col in suite.columns.iter().filter(|c| in_suite(&c.test)) {
+                    println!(
+                        "  column '{}' <- {} [{}]",
+                        col.column, col.test, col.filter()
+                    );
+                }
+                if config.pts_batch_setup {
+                    print_batch_plan();
+                }
+                if config.native_fallback {
+                    println!(
+                        "  if phoronix-test-suite is missing: native micro-benchmarks into the OpenSSL, RAMspeed and fio Seq columns (PTS Source = native), plus compression and AES-256 (file I/O in {})",
+                        std::env::temp_dir().display()
+                    );
+                }
+            }
+            PipelineStep::Browser => {
+                for target in config.browser_targets() {
+                    let caps: Vec<&str> = target.capabilities.keys().map(String::as_str).collect();
+                    println!(
+                        "  browser '{}' (capabilities: {})",
+                        target.name,
+                        if caps.is_empty() { "driver default".into() } else { caps.join(", ") }
+                    );
+                    match config.driver_for(&target) {
+                        (WebDriverMode::External, url) => println!("    WebDriver endpoint: {}", url),
+                        (mode, _) => match webdriver::find(mode) {
+                            Some(path) => println!(
+                                "    start {} on a free local port, stop it afterwards",
+                                path.display()
+                            ),
+                            None => println!("    webdriver = {}: not found on PATH", mode.as_str()),
+                        },
+                    }
+                }
+                let base = match &config.browser_suites {
+                    Some(dir) => {
+                        println!("  serve {} on a free 127.0.0.1 port while the step runs", dir);
+                        "http://127.0.0.1:<port>/"
+                    }
+                    None => browser_bench::BROWSERBENCH_URL,
+                };
+                let limit = config
+                    .timeouts
+                    .browser_suite()
+                    .unwrap_or(browser_bench::DEFAULT_SUITE_TIMEOUT);
+                for suite in config.browser_suite_list()? {
+                    println!(
+                        "  {} [{}]: {} ({}; up to {}s)",
+                        suite.name,
+                        config.suite_version(&suite),
+                        suite.url(base),
+                        suite.describe(),
+                        limit.as_secs()
+                    );
+                }
+            }
+            PipelineStep::Csv => {
+                println!("  CSV path: {}", config.csv_path);
+                let header = csv_row::header(
+                    &config.with_empty_columns(BenchResults::default()),
+                    config.csv_stats,
+                );
+                println!("  {}", csv_row::header_status(&config.csv_path, &header));
+            }
+            PipelineStep::Sheets => {
+                println!("  OAuth: {} / {}", google_auth::AUTH_URL, google_auth::TOKEN_URL);
+                let sheet_id = config.sheet_id.as_deref().unwrap_or_default();
+                println!("  POST {}", google_sheets::append_url(sheet_id));
+                sync::append_row_to_sheet_stub(sheet_id, "<row built from this run>")
+                    .map_err(|e| MjError::Other(e.to_string()))?;
+            }
+            PipelineStep::Drive => {
+                let folder_id = config.drive_folder_id.as_deref().unwrap_or_default();
+                println!("  POST {}", google_drive::UPLOAD_URL);
+                sync::upload_csv_to_drive_stub(folder_id, &config.csv_path)
+                    .map_err(|e| MjError::Other(e.to_string()))?;
+            }
+            PipelineStep::Done => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// What `user-config.xml` needs before an unattended run.
+fn print_batch_plan() {
+    let path = match pts_batch::config_path() {
+        Ok(path) => path,
+        Err(e) => return println!("  user-config.xml: {}", e),
+    };
+    if !path.exists() {
+        return println!(
+            "  create {} with unattended batch settings; removed after the run",
+            path.display()
+        );
+    }
+    let changes = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|xml| pts_batch::check(&xml));
+    match changes {
+        Ok(changes) if changes.is_empty() => {
+            println!("  {}: batch settings already set", path.display())
+        }
+        Ok(changes) => {
+            println!("  patch {} for the run, then restore it:", path.display());
+            for change in changes {
+                println!("    {}", change);
+            }
+        }
+        Err(e) => println!("  {}: {}", path.display(), e),
+    }
+}
+
+/// How a run starts: from scratch, or from a saved checkpoint.
+pub enum RunStart {
+    New(Box<PipelineConfig>),
+    Resume(Box<Checkpoint>),
+}
+
+/// Asks a running pipeline to stop. The running step is dropped (killing
+/// any PTS process group), reported as cancelled, and the run ends with
+/// its checkpoint saved for `resume`.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<watch::Sender<bool>>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        let _ = self.0.send(true);
+    }
+}
+
+pub struct Pipeline {
+    state: Checkpoint,
+    platform: Platform,
+    events: UnboundedSender<PipelineEvent>,
+    cancel: watch::Receiver<bool>,
+    token: Option<StoredToken>,
+}
+
+/// Start the pipeline on a background task and hand back its event stream
+/// and a handle to cancel it.
+pub fn spawn(
+    start: RunStart,
+) -> (JoinHandle<RunSummary>, UnboundedReceiver<PipelineEvent>, CancelHandle) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    let state = match start {
+        RunStart::New(config) => Checkpoint::new(*config),
+        RunStart::Resume(checkpoint) => *checkpoint,
+    };
+    let pipeline = Pipeline::new(state, tx, cancel_rx);
+    (tokio::spawn(pipeline.run()), rx, CancelHandle(Arc::new(cancel_tx)))
+}
+
+/// Resolves once `cancel` is set; never if its handle is gone.
+async fn cancelled(cancel: &mut watch::Receiver<bool>) {
+    if cancel.wait_for(|&c| c).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Resolves after `limit`, or never without one.
+async fn expired(limit: Option<Duration>) {
+    match limit {
+        Some(limit) => tokio::time::sleep(limit).await,
+        None => std::future::pending().await,
+    }
+}
+
+enum StepEnd {
+    Done(Result<(), MjError>),
+    Cancelled,
+}
+
+impl Pipeline {
+    pub fn new(
+        state: Checkpoint,
+        events: UnboundedSender<PipelineEvent>,
+        cancel: watch::Receiver<bool>,
+    ) -> Self {
+        Self {
+            state,
+            platform: platform::detect_platform(),
+            events,
+            cancel,
+            token: None,
+        }
+    }
+
+    pub async fn run(mut self) -> RunSummary {
+        let mut summary = RunSummary::default();
+        self.emit(PipelineEvent::RunStarted { run_id: self.state.run_id().to_string() });
+        self.log(format!("Platform: {}", self.platform));
+        self.save_checkpoint();
+
+        for step in PipelineStep::ALL {
+            if self.state.is_completed(step) {
+                self.restore(step);
+                summary.outcomes.push((step, StepOutcome::Restored));
+                continue;
+            }
+
+            if let Some(reason) = self.state.config.skip_reason(step) {
+                self.emit(PipelineEvent::StepSkipped(step, reason.clone()));
+                summary.outcomes.push((step, StepOutcome::Skipped(reason)));
+                continue;
+            }
+
+            self.emit(PipelineEvent::StepStarted(step));
+            let start = Instant::now();
+
+            match self.run_guarded(step).await {
+                StepEnd::Done(Ok(())) => {
+                    let elapsed = start.elapsed();
+                    self.state.completed.push(step);
+                    self.save_checkpoint();
+                    self.emit(PipelineEvent::StepCompleted(step, elapsed));
+                    summary.outcomes.push((step, StepOutcome::Completed(elapsed)));
+                }
+                StepEnd::Done(Err(err)) => {
+                    self.emit(PipelineEvent::StepFailed(step, err.clone()));
+                    summary.outcomes.push((step, StepOutcome::Failed(err)));
+                    break;
+                }
+                StepEnd::Cancelled => {
+                    self.log(format!("{} cancelled", step.label()));
+                    self.emit(PipelineEvent::StepCancelled(step));
+                    summary.outcomes.push((step, StepOutcome::Cancelled));
+                    break;
+                }
+            }
+        }
+
+        if self.state.metadata.finished_at.is_none() {
+            self.state.metadata.mark_finished();
+        }
+        summary.metadata = self.state.metadata.clone();
+        summary.specs = self.state.specs.clone();
+        summary.bench = self.state.bench.clone();
+        self.write_report(&summary);
+
+        self.emit(PipelineEvent::Finished(Box::new(summary.clone())));
+        summary
+    }
+
+    /// Run `step` until it finishes, times out or is cancelled. Losing
+    /// the race drops the step's future, which kills any child process
+    /// group it started; blocking work (spec collection, native
+    /// benchmarks) finishes in the background and is discarded.
+    async fn run_guarded(&mut self, step: PipelineStep) -> StepEnd {
+        let limit = self.state.config.timeouts.step(step);
+        let mut cancel = self.cancel.clone();
+        if *cancel.borrow() {
+            return StepEnd::Cancelled;
+        }
+
+        tokio::select! {
+            result = self.run_step(step) => StepEnd::Done(result),
+            _ = cancelled(&mut cancel) => StepEnd::Cancelled,
+            _ = expired(limit) => StepEnd::Done(Err(MjError::Timeout {
+                step: step.label().into(),
+                limit_s: limit.unwrap_or_default().as_secs(),
+            })),
+        }
+    }
+
+    fn write_report(&self, summary: &RunSummary) {
+        let Some(path) = &self.state.config.output_json else {
+            return;
+        };
+        match RunReport::from_summary(summary).write(path) {
+            Ok(()) => self.log(format!("Wrote JSON report to {}", path)),
+            Err(e) => self.log(format!("WARNING: could not write JSON report {}: {}", path, e)),
+        }
+    }
+
+    /// Replay a step finished in an earlier attempt to the front end.
+    fn restore(&self, step: PipelineStep) {
+        match step {
+            PipelineStep::Specs => {
+                if let Some(specs) = &self.state.specs {
+                    self.emit(PipelineEvent::SpecsCollected(Box::new(specs.clone())));
+                }
+            }
+            PipelineStep::Pts | PipelineStep::Browser => {
+                if let Some(bench) = &self.state.bench {
+                    self.emit(PipelineEvent::BenchUpdated(Box::new(bench.clone())));
+                }
+            }
+            _ => {}
+        }
+        self.emit(PipelineEvent::StepRestored(step));
+    }
+
+    fn save_checkpoint(&self) {
+        // A checkpoint we cannot write only costs resumability, not the run.
+        if let Err(e) = self.state.save() {
+            self.log(format!("WARNING: could not save checkpoint: {}", e));
+        }
+    }
+
+    async fn run_step(&mut self, step: PipelineStep) -> Result<(), MjError> {
+        match step {
+            PipelineStep::Specs => {
+                self.log("Collecting device specs…");
+                let platform = self.platform;
+                let specs = tokio::task::spawn_blocking(move || collect_specs(platform))
+                    .await
+                    .map_err(|e| MjError::Specs(e.to_string()))?;
+                self.emit(PipelineEvent::SpecsCollected(Box::new(specs.clone())));
+                self.state.specs = Some(specs);
+            }
+
+            PipelineStep::Pts => {
+                self.log("Running PTS benchmarks…");
+                let bench = match pts::ensure_pts_installed().await {
+                    Err(pts::PtsError::MissingPts) if self.state.config.native_fallback => {
+                        self.run_native().await?
+                    }
+                    installed => {
+                        installed?;
+                        self.run_pts_suite().await?
+                    }
+                };
+
+                self.emit(PipelineEvent::BenchUpdated(Box::new(bench.clone())));
+                self.state.bench = Some(bench);
+            }
+
+            PipelineStep::Browser => {
+                self.log("Running browser benchmarks…");
+                let (browsers, timed_out) = self.run_browsers().await?;
+                let first = browsers.first().cloned().unwrap_or_default();
+
+                // PTS may have been skipped; browser scores still get a row.
+                let bench = self.state.bench.get_or_insert_with(BenchResults::default);
+                bench.set_browsers(browsers);
+                let bench = bench.clone();
+                self.emit(PipelineEvent::BenchUpdated(Box::new(bench)));
+
+                let meta = &mut self.state.metadata;
+                meta.browser_name = first.browser_name;
+                meta.browser_version = first.browser_version;
+                meta.driver_version = first.driver_version;
+
+                // The other suites' scores stay in the report; resuming
+                // with a higher timeouts.browser_suite runs them all again.
+                if let Some(e) = timed_out {
+                    return Err(e.into());
+                }
+            }
+
+            PipelineStep::Csv => {
+                self.log(format!("Writing CSV row to {}…", self.state.config.csv_path));
+                // Skipped measurement steps leave their columns empty.
+                let specs = self.state.specs.clone().unwrap_or_default();
+                let bench = self
+                    .state
+                    .config
+                    .with_empty_columns(self.state.bench.clone().unwrap_or_default());
+
+                self.state.metadata.mark_finished();
+                let stats = self.state.config.csv_stats;
+                let row = csv_row::build_csv_row(&specs, &bench, &self.state.metadata, stats);
+                let path = &self.state.config.csv_path;
+                csv_row::append_to_csv(path, &bench, stats, &row)
+                    .map_err(|e| MjError::Csv(path.clone(), e.to_string()))?;
+                self.state.row = Some(row);
+            }
+
+            PipelineStep::Sheets => {
+                self.log("Appending row to Google Sheets…");
+                let token = self.token().await?;
+                let sheet_id = self
+                    .state
+                    .config
+                    .sheet_id
+                    .as_deref()
+                    .ok_or_else(|| MjError::Other("missing sheet ID".into()))?;
+                let row = self
+                    .state
+                    .row
+                    .as_ref()
+                    .ok_or_else(|| MjError::Other("no CSV row to sync; the CSV step did not run".into()))?;
+                google_sheets::append_row(sheet_id, row, &token).await?;
+            }
+
+            PipelineStep::Drive => {
+                self.log("Uploading CSV to Google Drive…");
+                let token = self.token().await?;
+                let folder_id = self
+                    .state
+                    .config
+                    .drive_folder_id
+                    .as_deref()
+                    .ok_or_else(|| MjError::Other("missing Drive folder ID".into()))?;
+                google_drive::upload_csv(folder_id, &self.state.config.csv_path, &token).await?;
+            }
+
+            PipelineStep::Done => {}
+        }
+
+        Ok(())
+    }
+
+    /// Authenticate once and reuse the token for every Google step.
+    async fn token(&mut self) -> Result<StoredToken, MjError> {
+        if let Some(tok) = &self.token {
+            return Ok(tok.clone());
+        }
+
+        let (Some(client_id), Some(client_secret)) =
+            (self.state.config.client_id.as_deref(), self.state.config.client_secret.as_deref())
+        else {
+            return Err(GoogleAuthError::MissingCredentials.into());
+        };
+
+        self.log("Authenticating with Google…");
+        let tok = google_auth::get_token(client_id, client_secret).await?;
+        self.token = Some(tok.clone());
+        Ok(tok)
+    }
+
+    async fn run_pts_suite(&mut self) -> Result<BenchResults, MjError> {
+        let suite = self.state.config.suite();
+        // Restored on drop too, when the step fails or is cancelled.
+        let batch = if self.state.config.pts_batch_setup {
+            Some(self.prepare_batch_mode()?)
+        } else {
+            None
+        };
+        pts::ensure_suite_exists(&suite).await?;
+
+        let run_id = self.state.run_id().to_string();
+        let bench = pts::run_suite(
+            &suite,
+            &run_id,
+            self.state.config.timeouts.pts_test(),
+            |line| self.log(line),
+            |progress| self.emit(PipelineEvent::PtsProgress(progress.clone())),
+        )
+        .await?;
+
+        self.state.metadata.pts_version = pts::version().await;
+        if let Some(batch) = batch.filter(pts_batch::BatchConfig::changed) {
+            let path = batch.path().display().to_string();
+            let created = batch.created();
+            match batch.restore() {
+                Ok(()) if created => self.log(format!(
+                    "Reset the PTS batch settings in {} to PTS's defaults",
+                    path
+                )),
+                Ok(()) => self.log(format!("Restored {}", path)),
+                Err(e) => self.log(format!(
+                    "WARNING: could not restore {} ({}); the next run restores it from its .mj-backup",
+                    path, e
+                )),
+            }
+        }
+        Ok(bench)
+    }
+
+    fn prepare_batch_mode(&self) -> Result<pts_batch::BatchConfig, MjError> {
+        let path = pts_batch::config_path()?;
+        let (batch, changes) = pts_batch::BatchConfig::apply(&path)?;
+        if changes.is_empty() {
+            self.log(format!("PTS batch settings already set in {}", path.display()));
+        } else {
+            self.log(format!("Set PTS batch settings in {} for this run:", path.display()));
+            for change in changes {
+                self.log(format!("  {}", change));
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Run the suites in each configured browser in turn. A browser that
+    /// fails is recorded without scores and the next one still runs; the
+    /// step fails only when every browser did. Also returns the first suite
+    /// timeout, which fails the step once every browser has run.
+    async fn run_browsers(&self) -> Result<(Vec<BrowserScores>, Option<BrowserError>), MjError> {
+        let config = &self.state.config;
+        let suites = config.browser_suite_list()?;
+
+        // Stopped when dropped, like a managed driver.
+        let server = match &config.browser_suites {
+            Some(dir) => {
+                let server = SuiteServer::start(Path::new(dir), &suites).await?;
+                self.log(format!("Serving browser suites from {} on {}", dir, server.url()));
+                Some(server)
+            }
+            None => None,
+        };
+        let suites_url = server.as_ref().map_or(browser_bench::BROWSERBENCH_URL.to_string(), |s| s.url());
+
+        let mut browsers = Vec::new();
+        let mut first_error = None;
+        let mut timed_out = None;
+        for target in config.browser_targets() {
+            if !config.browsers.is_empty() {
+                self.log(format!("Browser '{}'", target.name));
+            }
+            let browser = match self.run_browser(&target, &suites, &suites_url).await {
+                Ok(browser) => browser,
+                Err(e) => {
+                    self.log(format!("Browser '{}' failed: {}", target.name, e));
+                    browsers.push(BrowserScores {
+                        name: target.name,
+                        suites: empty_suite_scores(&suites),
+                        error: Some(e.to_string()),
+                        ..BrowserScores::default()
+                    });
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            timed_out = timed_out.or(browser.timed_out);
+            let mut scores = browser.suites;
+            for (score, suite) in scores.iter_mut().zip(&suites) {
+                score.version = Some(config.suite_version(suite));
+            }
+            browsers.push(BrowserScores {
+                name: target.name,
+                browser_name: browser.browser_name,
+                browser_version: browser.browser_version,
+                engine: browser.engine,
+                driver_version: browser.driver_version,
+                suites: scores,
+                error: None,
+            });
+        }
+        match first_error {
+            Some(e) if browsers.iter().all(|b| b.error.is_some()) => Err(e),
+            _ => Ok((browsers, timed_out)),
+        }
+    }
+
+    /// Run `suites`, opened under `suites_url`, in `target`'s browser,
+    /// starting and stopping its driver when mj-benchmark manages it.
+    async fn run_browser(
+        &self,
+        target: &BrowserTarget,
+        suites: &[BrowserSuite],
+        suites_url: &str,
+    ) -> Result<browser_bench::BrowserBenchResults, MjError> {
+        let config = &self.state.config;
+        let suite_timeout = config
+            .timeouts
+            .browser_suite()
+            .unwrap_or(browser_bench::DEFAULT_SUITE_TIMEOUT);
+        let (mode, url) = config.driver_for(target);
+
+        // Dropped (and so stopped) with this future if the step is cancelled.
+        let driver = match mode {
+            WebDriverMode::External => None,
+            mode => {
+                let driver = ManagedDriver::start(mode).await?;
+                self.log(format!(
+                    "Started {} on {}",
+                    driver.version().unwrap_or(mode.as_str()),
+                    driver.url()
+                ));
+                Some(driver)
+            }
+        };
+        let url = driver.as_ref().map_or(url, |d| d.url());
+
+        let browser = browser_bench::run_browser_benchmarks(
+            url,
+            &target.capabilities,
+            suites,
+            suites_url,
+            suite_timeout,
+            |line| self.log(line),
+        )
+        .await;
+
+        let Some(driver) = driver else {
+            return Ok(browser?);
+        };
+        let version = driver.version().map(str::to_string);
+        driver.stop().await;
+        self.log("Stopped the WebDriver server");
+
+        let mut browser = browser?;
+        // geckodriver's /status carries no version.
+        browser.driver_version = browser.driver_version.or(version);
+        Ok(browser)
+    }
+
+    /// The built-in fallback for machines without phoronix-test-suite;
+    /// its values are tagged native in every output.
+    async fn run_native(&mut self) -> Result<BenchResults, MjError> {
+        self.log("phoronix-test-suite not found; running native micro-benchmarks instead");
+        let events = self.events.clone();
+        let dir = std::env::temp_dir();
+        let native = tokio::task::spawn_blocking(move || {
+            native_bench::run(&dir, |line| {
+                let _ = events.send(PipelineEvent::Log(line.to_string()));
+            })
+        })
+        .await
+        .map_err(|e| MjError::Other(format!("native benchmark task failed: {}", e)))??;
+
+        let mut bench = BenchResults::default();
+        native_bench::apply(&mut bench, native);
+        Ok(bench)
+    }
+
+    fn log<S: Into<String>>(&self, msg: S) {
+        self.emit(PipelineEvent::Log(msg.into()));
+    }
+
+    fn emit(&self, event: PipelineEvent) {
+        // A front end that quit early just stops listening; keep running.
+        let _ = self.events.send(event);
+    }
+}
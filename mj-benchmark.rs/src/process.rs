@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Child processes that can be cancelled as a whole.
+//!
+//! `phoronix-test-suite` is a PHP script that starts each test's binaries
+//! as its own children, so killing the direct child leaves them running.
+//! On Unix every child here gets its own process group, and dropping its
+//! [`GroupGuard`] (because the step was cancelled or timed out) signals
+//! the whole group: SIGTERM, then SIGKILL after [`KILL_GRACE`]. Being in
+//! another group also keeps the terminal's Ctrl-C away from the children,
+//! so cancellation always goes through the pipeline. The SIGKILL is sent
+//! from a watcher thread, which [`wait_for_groups`] joins before the
+//! program exits.
+
+use std::ffi::OsStr;
+use std::io;
+use std::process::{Output, Stdio};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tokio::process::{Child, Command};
+
+/// How long a cancelled group gets to exit after SIGTERM.
+pub const KILL_GRACE: Duration = Duration::from_secs(3);
+
+#[cfg(unix)]
+/// How often a watcher checks whether a signalled group is gone.
+const GROUP_POLL: Duration = Duration::from_millis(50);
+
+/// Watchers that may still have to SIGKILL their group.
+static WATCHERS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// A `Command` for `program` whose process tree can be cancelled.
+pub fn command(program: impl AsRef<OsStr>) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+/// Kills a child's process group when dropped, unless disarmed after the
+/// child exited normally.
+pub struct GroupGuard {
+    pgid: Option<u32>,
+}
+
+impl GroupGuard {
+    pub fn new(child: &Child) -> Self {
+        Self { pgid: child.id() }
+    }
+
+    /// The child has been waited for; its group is gone.
+    pub fn disarm(&mut self) {
+        self.pgid = None;
+    }
+
+    /// Signal the group now rather than on drop.
+    pub fn terminate(&mut self) {
+        if let Some(pgid) = self.pgid.take() {
+            terminate_group(pgid);
+        }
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+#[cfg(unix)]
+fn terminate_group(pgid: u32) {
+    let Ok(pgid) = libc::pid_t::try_from(pgid) else {
+        return;
+    };
+    // SAFETY: kill(2) with a negative pid only signals that process group.
+    unsafe {
+        libc::kill(-pgid, libc::SIGTERM);
+    }
+    let watcher = std::thread::spawn(move || {
+        let started = std::time::Instant::now();
+        // SAFETY: signal 0 only checks that the group still exists.
+        while unsafe { libc::kill(-pgid, 0) } == 0 {
+            if started.elapsed() >= KILL_GRACE {
+                // SAFETY: as above.
+                unsafe {
+                    libc::kill(-pgid, libc::SIGKILL);
+                }
+                return;
+            }
+            std::thread::sleep(GROUP_POLL);
+        }
+    });
+    WATCHERS.lock().unwrap_or_else(|e| e.into_inner()).push(watcher);
+}
+
+/// Without process groups only the direct child is killed, by
+/// `kill_on_drop`.
+#[cfg(not(unix))]
+fn terminate_group(_pgid: u32) {}
+
+/// Block until every signalled group has exited or been sent SIGKILL.
+/// Called before the program exits, so a group that ignores SIGTERM
+/// cannot outlive it.
+pub fn wait_for_groups() {
+    let watchers = std::mem::take(&mut *WATCHERS.lock().unwrap_or_else(|e| e.into_inner()));
+    for watcher in watchers {
+        let _ = watcher.join();
+    }
+}
+
+/// `Command::output` with the group guard: dropping the future kills the
+/// whole tree.
+pub async fn output(cmd: &mut Command) -> io::Result<Output> {
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut guard = GroupGuard::new(&child);
+    let out = child.wait_with_output().await?;
+    guard.disarm();
+    Ok(out)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn groups_ignoring_sigterm_are_killed_before_exit() {
+        let mut child = command("sh")
+            .args(["-c", "trap '' TERM; sleep 30"])
+            .spawn()
+            .unwrap();
+        // Give the shell time to install the trap.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        drop(GroupGuard::new(&child));
+
+        tokio::task::spawn_blocking(wait_for_groups).await.unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(1), child.wait())
+            .await
+            .expect("group still running after wait_for_groups")
+            .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+}
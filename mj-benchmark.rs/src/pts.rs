@@ -9,22 +9,45 @@
 //   These names are trademarks of Mohamed Hammad and may not be used
 //   to endorse or promote derivative products without prior permission.
 
-use std::process::Command;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::fs;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::time::Instant;
 
 use crate::model::BenchResults;
+use crate::process::{self, GroupGuard};
+use crate::pts_progress::{ProgressParser, PtsProgress};
+use crate::pts_results::{self, PtsColumn};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Error)]
 pub enum PtsError {
+    #[error("phoronix-test-suite is not installed or not on PATH")]
     MissingPts,
+    #[error("command failed: {0}")]
     CommandFailed(String),
-    NoResultsFound,
+    #[error("no PTS result named {name} in {dir}")]
+    NoResultsFound { name: String, dir: String },
+    #[error("could not parse results: {0}")]
     ParseError(String),
+    #[error("PTS user config {0}: {1}")]
+    UserConfig(String, String),
+    #[error("PTS test {test} exceeded its {limit_s}s timeout")]
+    TestTimedOut { test: String, limit_s: u64 },
+    #[error("composite.xml has no result for {test} matching {filter} (column {column})")]
+    MissingResult {
+        column: String,
+        test: String,
+        filter: String,
+    },
 }
 
-fn run(cmd: &str, args: &[&str]) -> Result<String, PtsError> {
-    let out = Command::new(cmd).args(args).output()
+async fn run(cmd: &str, args: &[&str]) -> Result<String, PtsError> {
+    let out = process::output(process::command(cmd).args(args)).await
         .map_err(|e| PtsError::CommandFailed(format!("Failed to run {}: {}", cmd, e)))?;
 
     if !out.status.success() {
@@ -37,10 +60,101 @@ fn run(cmd: &str, args: &[&str]) -> Result<String, PtsError> {
     Ok(String::from_utf8_lossy(&out.stdout).to_string())
 }
 
-pub fn ensure_pts_installed() -> Result<(), PtsError> {
-    let status = Command::new("phoronix-test-suite")
-        .arg("version")
-        .output();
+/// Like `run`, but hands every stdout and stderr line to `on_line` as
+/// it is printed instead of collecting the output.
+///
+/// With `section_timeout`, the process is killed once that long passes
+/// without `on_line` returning true (PTS: a new test starting).
+async fn run_streaming(
+    cmd: &str,
+    args: &[&str],
+    envs: &[(&str, &str)],
+    section_timeout: Option<Duration>,
+    on_line: &mut impl FnMut(&str) -> bool,
+) -> Result<(), PtsError> {
+    let mut child = process::command(cmd)
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| PtsError::CommandFailed(format!("Failed to run {}: {}", cmd, e)))?;
+    // Returning early (timeout) or being dropped (cancel) kills the tree.
+    let mut guard = GroupGuard::new(&child);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    forward_lines(child.stdout.take(), tx.clone());
+    forward_lines(child.stderr.take(), tx);
+
+    let deadline = |limit: Duration| Instant::now() + limit;
+    let mut section_end = section_timeout.map(deadline);
+
+    // Ends once both pipes are closed.
+    loop {
+        let line = match section_end {
+            Some(end) => match tokio::time::timeout_at(end, rx.recv()).await {
+                Ok(line) => line,
+                Err(_) => {
+                    return Err(PtsError::TestTimedOut {
+                        test: String::new(),
+                        limit_s: section_timeout.unwrap_or_default().as_secs(),
+                    })
+                }
+            },
+            None => rx.recv().await,
+        };
+        let Some(line) = line else {
+            break;
+        };
+        if on_line(&line) {
+            section_end = section_timeout.map(deadline);
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| PtsError::CommandFailed(format!("Failed to wait for {}: {}", cmd, e)))?;
+    guard.disarm();
+    if !status.success() {
+        return Err(PtsError::CommandFailed(format!(
+            "{} {:?} exited with {}",
+            cmd, args, status
+        )));
+    }
+
+    Ok(())
+}
+
+fn forward_lines<R>(pipe: Option<R>, tx: UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let Some(pipe) = pipe else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(pipe).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// `phoronix-test-suite version`, e.g. "Phoronix Test Suite v10.8.4".
+pub async fn version() -> Option<String> {
+    let out = run("phoronix-test-suite", &["version"]).await.ok()?;
+    out.lines()
+        .map(str::trim)
+        .find(|l| l.contains("Phoronix Test Suite"))
+        .map(str::to_string)
+}
+
+pub async fn ensure_pts_installed() -> Result<(), PtsError> {
+    let status = process::output(process::command("phoronix-test-suite").arg("version")).await;
 
     match status {
         Ok(o) if o.status.success() => Ok(()),
@@ -48,63 +162,198 @@ pub fn ensure_pts_installed() -> Result<(), PtsError> {
     }
 }
 
-pub fn ensure_suite_exists() -> Result<(), PtsError> {
-    println!("Ensuring PTS suite 'mohamed-core' exists...");
+/// Test profiles bundled into the suite unless a config profile sets
+/// its own `pts_tests`.
+pub const DEFAULT_TESTS: &[&str] = &[
+    "pts/compress-7zip",
+    "pts/openssl",
+    "pts/ramspeed",
+    "pts/fio",
+    "pts/glmark2",
+    "pts/build-linux-kernel",
+];
+
+/// Default local suite name; a profile can set `pts_suite`.
+pub const SUITE_NAME: &str = "mohamed-core";
+
+/// The local PTS suite a run builds and runs, and how its results map to
+/// output columns.
+#[derive(Debug, Clone)]
+pub struct Suite {
+    pub name: String,
+    pub tests: Vec<String>,
+    /// Built-in mappings merged with the profile's `pts_columns`.
+    pub columns: Vec<PtsColumn>,
+}
+
+fn build_suite_args(suite: &Suite) -> Vec<&str> {
+    let mut args = vec!["build-suite", suite.name.as_str()];
+    args.extend(suite.tests.iter().map(String::as_str));
+    args
+}
 
+pub async fn ensure_suite_exists(suite: &Suite) -> Result<(), PtsError> {
     // Build suite with your chosen tests
-    run("phoronix-test-suite", &[
-        "build-suite",
-        "mohamed-core",
-        "pts/compress-7zip",
-        "pts/openssl",
-        "pts/ramspeed",
-        "pts/fio",
-        "pts/glmark2",
-        "pts/build-linux-kernel",
-    ])?;
+    run("phoronix-test-suite", &build_suite_args(suite)).await?;
 
     Ok(())
 }
 
-pub fn run_suite() -> Result<BenchResults, PtsError> {
-    println!("Running PTS suite 'mohamed-core'...");
+/// The exact `phoronix-test-suite` invocations a run would make, for
+/// `run --dry-run`.
+pub fn planned_commands(suite: &Suite) -> Vec<String> {
+    let name = result_name("<run-id>");
+    let results = results_dir()
+        .map(|d| d.join(&name).display().to_string())
+        .unwrap_or_else(|e| e.to_string());
+    vec![
+        "phoronix-test-suite version".into(),
+        format!("phoronix-test-suite {}", build_suite_args(suite).join(" ")),
+        format!(
+            "TEST_RESULTS_NAME={} TEST_RESULTS_IDENTIFIER=<run-id> phoronix-test-suite batch-benchmark {}",
+            name, suite.name
+        ),
+        format!("phoronix-test-suite result-file-to-xml {}", name),
+        format!("parse {}/composite.xml", results),
+    ]
+}
+
+/// Run the suite, passing PTS's output to `on_line` line by line and
+/// its parsed progress to `on_progress`.
+///
+/// Results are saved under a name derived from `run_id`, so this run
+/// parses its own results even if other PTS runs finish meanwhile. A test
+/// running longer than `test_timeout` kills PTS and fails the suite.
+pub async fn run_suite(
+    suite: &Suite,
+    run_id: &str,
+    test_timeout: Option<Duration>,
+    mut on_line: impl FnMut(&str),
+    mut on_progress: impl FnMut(&PtsProgress),
+) -> Result<BenchResults, PtsError> {
+    let name = fresh_result_name(run_id)?;
+    on_line(&format!("Running PTS suite '{}' (results: {})...", suite.name, name));
+
+    let mut parser = ProgressParser::default();
+    let mut current_test = 0;
+    let streamed = run_streaming(
+        "phoronix-test-suite",
+        &["batch-benchmark", &suite.name],
+        &[("TEST_RESULTS_NAME", &name), ("TEST_RESULTS_IDENTIFIER", run_id)],
+        test_timeout,
+        &mut |line| {
+            on_line(line);
+            if !parser.feed(line) {
+                return false;
+            }
+            let progress = parser.progress();
+            on_progress(progress);
+            // A new "Test X of Y" restarts the per-test clock.
+            let new_test = progress.test_index != current_test;
+            current_test = progress.test_index;
+            new_test
+        },
+    )
+    .await;
+    streamed.map_err(|e| match e {
+        PtsError::TestTimedOut { limit_s, .. } => PtsError::TestTimedOut {
+            test: parser.progress().test.clone(),
+            limit_s,
+        },
+        e => e,
+    })?;
+
+    let result_dir = find_result_dir(&name)?;
+    on_line(&format!("PTS result directory: {:?}", result_dir));
 
-    run("phoronix-test-suite", &[
-        "batch-benchmark",
-        "mohamed-core",
-    ])?;
+    let xml_path = convert_to_xml(&name, &result_dir).await?;
+    on_line(&format!("Converted XML: {:?}", xml_path));
 
-    let result_dir = find_latest_result_dir()?;
-    println!("Latest PTS result directory: {:?}", result_dir);
+    // The run's entries are the ones under its TEST_RESULTS_IDENTIFIER.
+    let (bench, warnings) = parse_xml_results(&xml_path, suite, run_id)?;
+    for warning in &warnings {
+        on_line(&format!("warning: {}; column left empty", warning));
+    }
+    Ok(bench)
+}
+
+/// PTS's user directory: `$PTS_USER_PATH_OVERRIDE` if set, else
+/// `~/.phoronix-test-suite`.
+pub fn user_path() -> Result<PathBuf, PtsError> {
+    if let Some(dir) = std::env::var_os("PTS_USER_PATH_OVERRIDE").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| {
+            PtsError::CommandFailed("HOME is not set; set PTS_USER_PATH_OVERRIDE".into())
+        })?;
+    Ok(Path::new(&home).join(".phoronix-test-suite"))
+}
 
-    let xml_path = convert_to_xml(&result_dir)?;
-    println!("Converted XML: {:?}", xml_path);
+pub fn results_dir() -> Result<PathBuf, PtsError> {
+    Ok(user_path()?.join("test-results"))
+}
 
-    parse_xml_results(&xml_path)
+/// The saved-result name for a run. PTS lowercases names and keeps
+/// letters, digits and '-', which a UUID run ID already satisfies.
+pub fn result_name(run_id: &str) -> String {
+    format!("mj-{}", run_id)
 }
 
-fn find_latest_result_dir() -> Result<PathBuf, PtsError> {
-    let home = std::env::var("HOME").unwrap_or("".into());
-    let pts_dir = Path::new(&home).join(".phoronix-test-suite/test-results");
+/// `result_name`, suffixed when a resumed run already left results
+/// under it, so an earlier attempt's partial results are not mixed in.
+fn fresh_result_name(run_id: &str) -> Result<String, PtsError> {
+    let dir = results_dir()?;
+    let base = result_name(run_id);
+    let name = std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}-{}", base, n)))
+        .find(|name| !dir.join(name).exists())
+        .unwrap_or(base);
+    Ok(name)
+}
 
-    let mut entries: Vec<_> = fs::read_dir(&pts_dir)
-        .map_err(|_| PtsError::NoResultsFound)?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_dir())
-        .collect();
+fn find_result_dir(name: &str) -> Result<PathBuf, PtsError> {
+    let dir = results_dir()?;
+    let result_dir = dir.join(name);
+    if result_dir.is_dir() {
+        Ok(result_dir)
+    } else {
+        Err(PtsError::NoResultsFound {
+            name: name.to_string(),
+            dir: dir.display().to_string(),
+        })
+    }
+}
 
-    entries.sort_by_key(|e| e.metadata().unwrap().modified().unwrap());
+/// The composite.xml for `source`: a saved result name (as in
+/// `phoronix-test-suite list-saved-results`), a result directory, or the
+/// file itself.
+pub fn locate_composite(source: &str) -> Result<PathBuf, PtsError> {
+    let path = Path::new(source);
+    let path = if path.exists() {
+        path.to_path_buf()
+    } else {
+        results_dir()?.join(source)
+    };
+    let xml_path = if path.is_dir() {
+        path.join("composite.xml")
+    } else {
+        path
+    };
 
-    let latest = entries.last().ok_or(PtsError::NoResultsFound)?;
-    Ok(latest.path())
+    if xml_path.is_file() {
+        Ok(xml_path)
+    } else {
+        Err(PtsError::NoResultsFound {
+            name: source.to_string(),
+            dir: results_dir()?.display().to_string(),
+        })
+    }
 }
 
-fn convert_to_xml(result_dir: &Path) -> Result<PathBuf, PtsError> {
-    let name = result_dir.file_name().unwrap().to_string_lossy();
-    let xml = run("phoronix-test-suite", &[
-        "result-file-to-xml",
-        &name,
-    ])?;
+async fn convert_to_xml(name: &str, result_dir: &Path) -> Result<PathBuf, PtsError> {
+    run("phoronix-test-suite", &["result-file-to-xml", name]).await?;
 
     let xml_path = result_dir.join("composite.xml");
     if xml_path.exists() {
@@ -114,47 +363,14 @@ fn convert_to_xml(result_dir: &Path) -> Result<PathBuf, PtsError> {
     }
 }
 
-fn parse_xml_results(xml_path: &Path) -> Result<BenchResults, PtsError> {
+fn parse_xml_results(
+    xml_path: &Path,
+    suite: &Suite,
+    run_id: &str,
+) -> Result<(BenchResults, Vec<PtsError>), PtsError> {
     let xml = fs::read_to_string(xml_path)
         .map_err(|e| PtsError::ParseError(format!("Failed to read XML: {}", e)))?;
 
-    // VERY SIMPLE extraction — you can refine later
-    let seven_zip = extract_metric(&xml, "7-Zip Compression");
-    let openssl = extract_metric(&xml, "OpenSSL");
-    let ramspeed = extract_metric(&xml, "RAMspeed");
-    let fio_seq_read = extract_metric(&xml, "FIO Sequential Read");
-    let fio_seq_write = extract_metric(&xml, "FIO Sequential Write");
-    let glmark2 = extract_metric(&xml, "GLMark2");
-    let kernel_build = extract_metric(&xml, "Timed Linux Kernel Compilation");
-
-    Ok(BenchResults {
-        seven_zip_mips: seven_zip,
-        openssl_mb_s: openssl,
-        ramspeed_mb_s: ramspeed,
-        fio_seq_read_mb_s: fio_seq_read,
-        fio_seq_write_mb_s: fio_seq_write,
-        fio_rand_read_iops: None,
-        fio_rand_write_iops: None,
-        glmark2_score: glmark2,
-        kernel_build_time_s: kernel_build,
-        speedometer_score: None,
-        jetstream_score: None,
-        motionmark_score: None,
-        battery_full_wh: None,
-        battery_design_wh: None,
-        battery_health_percent: None,
-        battery_cycle_count: None,
-        notes: "PTS results parsed from XML".into(),
-    })
-}
-
-fn extract_metric(xml: &str, test_name: &str) -> Option<f64> {
-    let start = xml.find(test_name)?;
-    let slice = &xml[start..];
-
-    // Look for <value>...</value>
-    let val_start = slice.find("<value>")? + "<value>".len();
-    let val_end = slice[val_start..].find("</value>")? + val_start;
-
-    slice[val_start..val_end].trim().parse().ok()
-}
\ No newline at end of file
+    let results = pts_results::parse_composite(&xml, Some(run_id))?;
+    pts_results::to_bench_results(&results, &suite.tests, &suite.columns)
+}
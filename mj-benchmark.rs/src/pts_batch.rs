@@ -0,0 +1,459 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! PTS batch-mode settings in `user-config.xml`.
+//!
+//! `batch-benchmark` only runs unattended once `batch-setup` has been
+//! answered; until then it prompts, and with the defaults it may upload
+//! results to OpenBenchmarking.org. Before a run the settings below are
+//! patched in (only the ones that differ, keeping the rest of the file),
+//! and the original file is put back afterwards (a file that did not
+//! exist keeps whatever PTS wrote to it, with the settings back at PTS's
+//! defaults).
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+
+use crate::pts::{self, PtsError};
+
+const ROOT: &str = "PhoronixTestSuite";
+const OPTIONS: &str = "Options";
+
+/// `(section under <Options>, key, value, PTS default)` for an unattended
+/// run: no prompts (the result name comes from TEST_RESULTS_NAME), no
+/// uploads, every option combination, and each test's own run count
+/// instead of extra runs when results vary.
+pub const SETTINGS: &[(&str, &str, &str, &str)] = &[
+    ("BatchMode", "SaveResults", "TRUE", "TRUE"),
+    ("BatchMode", "OpenBrowser", "FALSE", "FALSE"),
+    ("BatchMode", "UploadResults", "FALSE", "TRUE"),
+    ("BatchMode", "PromptForTestIdentifier", "FALSE", "TRUE"),
+    ("BatchMode", "PromptForTestDescription", "FALSE", "TRUE"),
+    ("BatchMode", "PromptSaveName", "FALSE", "TRUE"),
+    ("BatchMode", "RunAllTestCombinations", "TRUE", "TRUE"),
+    ("BatchMode", "Configured", "TRUE", "FALSE"),
+    ("OpenBenchmarking", "AllowResultUploadsToOpenBenchmarking", "FALSE", "TRUE"),
+    ("OpenBenchmarking", "AnonymousUsageReporting", "FALSE", "TRUE"),
+    ("TestResultValidation", "DynamicRunCount", "FALSE", "TRUE"),
+];
+
+/// Written in place of a missing `user-config.xml`; PTS fills in the
+/// rest with its defaults.
+const SKELETON: &str = "<?xml version=\"1.0\"?>\n<PhoronixTestSuite>\n  <Options>\n  </Options>\n</PhoronixTestSuite>\n";
+
+/// A setting that differs from what an unattended run needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub section: &'static str,
+    pub key: &'static str,
+    /// `None` when the file does not set it.
+    pub current: Option<String>,
+    pub wanted: &'static str,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} -> {}",
+            self.section,
+            self.key,
+            self.current.as_deref().unwrap_or("(unset)"),
+            self.wanted
+        )
+    }
+}
+
+pub fn config_path() -> Result<PathBuf, PtsError> {
+    Ok(pts::user_path()?.join("user-config.xml"))
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("xml.mj-backup")
+}
+
+/// The settings in `xml` that need changing.
+pub fn check(xml: &str) -> Result<Vec<Change>, String> {
+    let doc = parse(xml)?;
+    let options = options_node(&doc)?;
+
+    Ok(SETTINGS
+        .iter()
+        .filter_map(|&(section, key, wanted, _)| {
+            let current = options
+                .and_then(|o| child(o, section))
+                .and_then(|s| child(s, key))
+                .map(|k| k.text().unwrap_or_default().trim().to_string());
+            (current.as_deref() != Some(wanted)).then_some(Change {
+                section,
+                key,
+                current,
+                wanted,
+            })
+        })
+        .collect())
+}
+
+/// `xml` with every setting applied, otherwise unchanged.
+pub fn patch(xml: &str) -> Result<String, String> {
+    let mut xml = if xml.trim().is_empty() {
+        SKELETON.to_string()
+    } else {
+        xml.to_string()
+    };
+    for &(section, key, value, _) in SETTINGS {
+        xml = set(&xml, [OPTIONS, section, key], value)?;
+    }
+    Ok(xml)
+}
+
+/// `xml` with every setting back at PTS's default, otherwise unchanged.
+pub fn reset(xml: &str) -> Result<String, String> {
+    let mut xml = xml.to_string();
+    for &(section, key, _, default) in SETTINGS {
+        xml = set(&xml, [OPTIONS, section, key], default)?;
+    }
+    Ok(xml)
+}
+
+/// Each pass makes one text edit (open up an element, add the missing
+/// part of the path, or replace the value) and re-parses.
+fn set(xml: &str, path: [&str; 3], value: &str) -> Result<String, String> {
+    let mut xml = xml.to_string();
+    for _ in 0..4 {
+        let edit = {
+            let doc = parse(&xml)?;
+            next_edit(&doc, &xml, &path, value)?
+        };
+        match edit {
+            Some((range, text)) => xml.replace_range(range, &text),
+            None => return Ok(xml),
+        }
+    }
+    Err(format!("could not set {}", path.join("/")))
+}
+
+fn next_edit(
+    doc: &Document,
+    xml: &str,
+    path: &[&str],
+    value: &str,
+) -> Result<Option<(Range<usize>, String)>, String> {
+    let mut node = root(doc)?;
+    for (depth, name) in path.iter().enumerate() {
+        match child(node, name) {
+            Some(next) => node = next,
+            None => return Ok(Some(insert(xml, node, &path[depth..], value))),
+        }
+    }
+
+    let current = node.text().unwrap_or_default().trim();
+    if current == value && !node.children().any(|c| c.is_element()) {
+        return Ok(None);
+    }
+    let key = path[path.len() - 1];
+    Ok(Some((node.range(), format!("<{0}>{1}</{0}>", key, value))))
+}
+
+/// The edit adding `names` (outermost first, the last holding `value`)
+/// as the last child of `parent`, indented like the file.
+fn insert(xml: &str, parent: Node, names: &[&str], value: &str) -> (Range<usize>, String) {
+    let range = parent.range();
+    let tag = parent.tag_name().name();
+    let indent = line_indent(xml, range.start);
+
+    // `<Options/>` or `<Options></Options>` on one line: put the closing
+    // tag on its own line first.
+    let close = format!("</{}>", tag);
+    if !xml[range.clone()].ends_with(&close) {
+        return (range, format!("<{0}>\n{1}</{0}>", tag, indent));
+    }
+    let close_start = range.end - close.len();
+    if line_indent(xml, close_start).len() != close_start - line_start(xml, close_start) {
+        return (close_start..close_start, format!("\n{}", indent));
+    }
+
+    let mut lines = Vec::new();
+    let (outer, leaf) = names.split_at(names.len() - 1);
+    for (depth, name) in outer.iter().enumerate() {
+        lines.push(format!("{}{}<{}>", indent, "  ".repeat(depth + 1), name));
+    }
+    lines.push(format!(
+        "{}{}<{2}>{3}</{2}>",
+        indent,
+        "  ".repeat(outer.len() + 1),
+        leaf[0],
+        value
+    ));
+    for (depth, name) in outer.iter().enumerate().rev() {
+        lines.push(format!("{}{}</{}>", indent, "  ".repeat(depth + 1), name));
+    }
+
+    let at = line_start(xml, close_start);
+    (at..at, format!("{}\n", lines.join("\n")))
+}
+
+fn line_start(xml: &str, pos: usize) -> usize {
+    xml[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// The whitespace before `pos` on its line.
+fn line_indent(xml: &str, pos: usize) -> &str {
+    let start = line_start(xml, pos);
+    let prefix = &xml[start..pos];
+    let ws = prefix.len() - prefix.trim_start().len();
+    &prefix[..ws]
+}
+
+fn parse(xml: &str) -> Result<Document<'_>, String> {
+    Document::parse(xml).map_err(|e| e.to_string())
+}
+
+fn root<'a, 'input>(doc: &'a Document<'input>) -> Result<Node<'a, 'input>, String> {
+    let root = doc.root_element();
+    if root.has_tag_name(ROOT) {
+        Ok(root)
+    } else {
+        Err(format!(
+            "root element is <{}>, expected <{}>",
+            root.tag_name().name(),
+            ROOT
+        ))
+    }
+}
+
+fn options_node<'a, 'input>(doc: &'a Document<'input>) -> Result<Option<Node<'a, 'input>>, String> {
+    Ok(child(root(doc)?, OPTIONS))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+/// Patched settings, put back by [`BatchConfig::restore`], or on drop if
+/// the run ends early (error, timeout, cancel).
+#[derive(Debug)]
+pub struct BatchConfig {
+    path: PathBuf,
+    /// What the file looked like: `None` when nothing was changed,
+    /// `Some(false)` when it did not exist. PTS writes its own settings to
+    /// a new file too, so that one is kept with the settings reset to
+    /// PTS's defaults rather than removed.
+    original: Option<bool>,
+}
+
+impl BatchConfig {
+    /// Patch the file at `path` for unattended runs, backing up the
+    /// original next to it. A backup left by a run that died is restored
+    /// first, so it is never taken for the user's own settings.
+    pub fn apply(path: &Path) -> Result<(Self, Vec<Change>), PtsError> {
+        let err = |e: String| PtsError::UserConfig(path.display().to_string(), e);
+        let io_err = |e: io::Error| err(e.to_string());
+
+        let backup = backup_path(path);
+        if backup.exists() {
+            fs::rename(&backup, path).map_err(io_err)?;
+        }
+
+        let existed = path.exists();
+        let xml = if existed {
+            fs::read_to_string(path).map_err(io_err)?
+        } else {
+            SKELETON.to_string()
+        };
+        let changes = check(&xml).map_err(err)?;
+        let mut config = BatchConfig {
+            path: path.to_path_buf(),
+            original: None,
+        };
+        if changes.is_empty() {
+            return Ok((config, changes));
+        }
+
+        let patched = patch(&xml).map_err(err)?;
+        if existed {
+            fs::copy(path, &backup).map_err(io_err)?;
+        } else if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        config.original = Some(existed);
+
+        // Write beside it and rename, so PTS never reads half a file.
+        let tmp = path.with_extension("xml.mj-tmp");
+        fs::write(&tmp, patched).and_then(|()| fs::rename(&tmp, path)).map_err(io_err)?;
+        Ok((config, changes))
+    }
+
+    /// Whether the file was changed and will be restored.
+    pub fn changed(&self) -> bool {
+        self.original.is_some()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file did not exist before, so restoring resets the
+    /// settings to PTS's defaults instead of putting back a backup.
+    pub fn created(&self) -> bool {
+        self.original == Some(false)
+    }
+
+    pub fn restore(mut self) -> io::Result<()> {
+        self.restore_original()
+    }
+
+    fn restore_original(&mut self) -> io::Result<()> {
+        match self.original.take() {
+            Some(true) => fs::rename(backup_path(&self.path), &self.path),
+            Some(false) => {
+                let xml = match fs::read_to_string(&self.path) {
+                    Ok(xml) => xml,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                    Err(e) => return Err(e),
+                };
+                let xml = reset(&xml).map_err(io::Error::other)?;
+                let tmp = self.path.with_extension("xml.mj-tmp");
+                fs::write(&tmp, xml).and_then(|()| fs::rename(&tmp, &self.path))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for BatchConfig {
+    fn drop(&mut self) {
+        // A failed restore leaves the backup for the next run to restore.
+        let _ = self.restore_original();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT_CONFIG: &str = include_str!("../tests/fixtures/pts/user-config.xml");
+
+    fn value(xml: &str, section: &str, key: &str) -> Option<String> {
+        let doc = Document::parse(xml).unwrap();
+        let options = child(doc.root_element(), OPTIONS)?;
+        let node = child(child(options, section)?, key)?;
+        Some(node.text().unwrap_or_default().to_string())
+    }
+
+    #[test]
+    fn default_config_needs_prompts_and_uploads_off() {
+        let changes = check(DEFAULT_CONFIG).unwrap();
+        let keys: Vec<&str> = changes.iter().map(|c| c.key).collect();
+        assert_eq!(
+            keys,
+            [
+                "UploadResults",
+                "PromptForTestIdentifier",
+                "PromptForTestDescription",
+                "PromptSaveName",
+                "Configured",
+                "AllowResultUploadsToOpenBenchmarking",
+                "AnonymousUsageReporting",
+                "DynamicRunCount",
+            ]
+        );
+        assert_eq!(changes[0].to_string(), "BatchMode/UploadResults: TRUE -> FALSE");
+    }
+
+    #[test]
+    fn patch_sets_values_and_keeps_the_rest() {
+        let patched = patch(DEFAULT_CONFIG).unwrap();
+        assert!(check(&patched).unwrap().is_empty());
+        assert_eq!(value(&patched, "BatchMode", "UploadResults").as_deref(), Some("FALSE"));
+        assert_eq!(value(&patched, "Networking", "Timeout").as_deref(), Some("20"));
+        assert!(patched.starts_with("<?xml version=\"1.0\"?>\n<!--Phoronix Test Suite v10.8.4-->"));
+        // Only the changed lines differ.
+        let differing = DEFAULT_CONFIG
+            .lines()
+            .zip(patched.lines())
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(differing, 8);
+        assert_eq!(patch(&patched).unwrap(), patched);
+    }
+
+    #[test]
+    fn missing_sections_are_added_indented() {
+        let xml = "<PhoronixTestSuite>\n  <Options>\n    <General>\n      <ColoredConsole>AUTO</ColoredConsole>\n    </General>\n  </Options>\n</PhoronixTestSuite>\n";
+        let patched = patch(xml).unwrap();
+        assert!(check(&patched).unwrap().is_empty());
+        assert!(patched.contains("\n    <BatchMode>\n      <SaveResults>TRUE</SaveResults>\n"));
+        assert!(patched.contains("\n      <DynamicRunCount>FALSE</DynamicRunCount>\n    </TestResultValidation>\n  </Options>\n"));
+
+        for xml in ["", "<PhoronixTestSuite/>", "<PhoronixTestSuite><Options></Options></PhoronixTestSuite>"] {
+            let patched = patch(xml).unwrap();
+            assert!(check(&patched).unwrap().is_empty(), "{:?} -> {}", xml, patched);
+        }
+    }
+
+    #[test]
+    fn other_roots_are_rejected() {
+        assert!(check("<Config/>").unwrap_err().contains("expected <PhoronixTestSuite>"));
+        assert!(patch("<Options>").is_err());
+    }
+
+    #[test]
+    fn apply_backs_up_and_restores() {
+        let dir = std::env::temp_dir().join(format!("mj-pts-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("user-config.xml");
+        fs::write(&path, DEFAULT_CONFIG).unwrap();
+
+        let (config, changes) = BatchConfig::apply(&path).unwrap();
+        assert!(config.changed());
+        assert_eq!(changes.len(), 8);
+        assert!(check(&fs::read_to_string(&path).unwrap()).unwrap().is_empty());
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), DEFAULT_CONFIG);
+
+        // Dropped without restore(), as when a run is cancelled.
+        drop(config);
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+        assert!(!backup_path(&path).exists());
+
+        // A backup left by a crashed run wins over the patched file.
+        fs::write(&path, patch(DEFAULT_CONFIG).unwrap()).unwrap();
+        fs::write(backup_path(&path), DEFAULT_CONFIG).unwrap();
+        let (config, _) = BatchConfig::apply(&path).unwrap();
+        config.restore().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+
+        // A file that did not exist is kept, with PTS's defaults back.
+        fs::remove_file(&path).unwrap();
+        let (config, changes) = BatchConfig::apply(&path).unwrap();
+        assert!(config.created());
+        assert_eq!(changes.len(), SETTINGS.len());
+        assert!(changes.iter().all(|c| c.current.is_none()));
+        config.restore().unwrap();
+        let restored = fs::read_to_string(&path).unwrap();
+        for &(section, key, _, default) in SETTINGS {
+            assert_eq!(value(&restored, section, key).as_deref(), Some(default), "{}", key);
+        }
+
+        // Settings PTS wrote during the run survive.
+        fs::remove_file(&path).unwrap();
+        let (config, _) = BatchConfig::apply(&path).unwrap();
+        fs::write(&path, patch(DEFAULT_CONFIG).unwrap()).unwrap();
+        drop(config);
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
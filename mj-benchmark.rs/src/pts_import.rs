@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! `mj-benchmark import-pts`: CSV rows for PTS results saved earlier, on
+//! this machine or another, without re-running anything.
+//!
+//! Results go through the same column mapping as a live run. Device specs
+//! cannot be recovered from a result file, so they come from a saved
+//! `mj-benchmark specs --format json` file and/or `--spec key=value`.
+//! A result's run ID is derived from the result itself, so importing it
+//! again is noticed and skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::csv_row;
+use crate::error::MjError;
+use crate::metadata::{self, RunMetadata};
+use crate::model::{BenchResults, DeviceSpecs};
+use crate::pipeline::PipelineConfig;
+use crate::pts::{self, PtsError};
+use crate::pts_results::{self, PtsColumn, PtsRunInfo};
+
+/// One parsed result file, ready to become a CSV row.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    pub path: PathBuf,
+    pub metadata: RunMetadata,
+    pub bench: BenchResults,
+    /// Columns whose test is in the file but matched no result; they are
+    /// left empty.
+    pub missing: Vec<PtsError>,
+    /// Its run ID was already in the CSV, so no row was written.
+    pub duplicate: bool,
+}
+
+/// Parse every source first, then append one row each, so a bad source
+/// leaves the CSV untouched. Results already in the CSV are skipped.
+pub fn import_all(
+    sources: &[String],
+    specs: &DeviceSpecs,
+    config: &PipelineConfig,
+) -> Result<Vec<Imported>, MjError> {
+    let columns = config.suite().columns;
+    let mut imported = sources
+        .iter()
+        .map(|source| import(source, &columns, &config.timezone))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Same columns as rows from `run` with this profile.
+    for item in &mut imported {
+        item.bench.browsers = config.empty_browser_scores();
+    }
+
+    let path = &config.csv_path;
+    let mut present = csv_row::run_ids(path).map_err(|e| MjError::Csv(path.clone(), e.to_string()))?;
+    for item in &mut imported {
+        if !present.insert(item.metadata.run_id.clone()) {
+            item.duplicate = true;
+            continue;
+        }
+        let row = csv_row::build_csv_row(specs, &item.bench, &item.metadata, config.csv_stats);
+        csv_row::append_to_csv(path, &item.bench, config.csv_stats, &row)
+            .map_err(|e| MjError::Csv(path.clone(), e.to_string()))?;
+    }
+
+    Ok(imported)
+}
+
+/// PTS timestamps carry no zone; they are read as `timezone`.
+pub fn import(source: &str, columns: &[PtsColumn], timezone: &str) -> Result<Imported, MjError> {
+    let path = pts::locate_composite(source)?;
+    let xml = fs::read_to_string(&path).map_err(|e| {
+        PtsError::ParseError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    // Merged files: the first system, which `parse_run_info` describes.
+    let results = pts_results::parse_composite(&xml, None)?;
+    if results.is_empty() {
+        return Err(PtsError::ParseError(format!("{} has no results", path.display())).into());
+    }
+    let info = pts_results::parse_run_info(&xml)?;
+
+    let (mut bench, missing) = pts_results::import_bench_results(&results, columns);
+    bench.notes = format!("Imported from {} ({})", path.display(), info.identifier);
+
+    let local = |time: &str| {
+        metadata::format_local(time, timezone).or_else(|| (!time.is_empty()).then(|| time.to_string()))
+    };
+    let metadata = RunMetadata {
+        run_id: run_id(&info),
+        started_at: local(&info.timestamp).unwrap_or_default(),
+        finished_at: local(&info.last_modified),
+        timezone: timezone.into(),
+        mj_version: env!("CARGO_PKG_VERSION").into(),
+        pts_version: (!info.test_client.is_empty()).then(|| info.test_client.clone()),
+        kernel: info.component("Kernel").unwrap_or_default().into(),
+        ..RunMetadata::default()
+    };
+
+    Ok(Imported {
+        path,
+        metadata,
+        bench,
+        missing,
+        duplicate: false,
+    })
+}
+
+/// A UUID fixed by what the result file says about the run: the same
+/// result imported twice gets the same run ID.
+fn run_id(info: &PtsRunInfo) -> String {
+    let digest = Sha256::digest(
+        [&info.title, &info.identifier, &info.timestamp, &info.hardware, &info.software]
+            .map(String::as_str)
+            .join("\n"),
+    );
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string()
+}
+
+/// Specs from `path` (a `DeviceSpecs` object, or the output of `mj-benchmark
+/// specs --format json`) with `overrides` on top. Fields set by neither
+/// stay empty.
+pub fn load_specs(path: Option<&Path>, overrides: &[(String, String)]) -> Result<DeviceSpecs, MjError> {
+    // Every field starts empty so a partial file still deserializes.
+    let mut fields: Map<String, Value> = DeviceSpecs::default()
+        .fields()
+        .iter()
+        .map(|(key, _, _)| (key.to_string(), Value::String(String::new())))
+        .collect();
+
+    if let Some(path) = path {
+        let data = fs::read_to_string(path)
+            .map_err(|e| MjError::Import(format!("cannot read {}: {}", path.display(), e)))?;
+        let mut doc: Value = serde_json::from_str(&data)
+            .map_err(|e| MjError::Import(format!("{} is not valid JSON: {}", path.display(), e)))?;
+        let doc = match doc.get_mut("specs") {
+            Some(specs) => specs.take(),
+            None => doc,
+        };
+        let Value::Object(doc) = doc else {
+            return Err(MjError::Import(format!("{} is not a JSON object", path.display())));
+        };
+        for (key, value) in doc {
+            let Value::String(value) = value else {
+                return Err(MjError::Import(format!(
+                    "{}: spec field '{}' is not a string",
+                    path.display(),
+                    key
+                )));
+            };
+            set_field(&mut fields, &key, value)?;
+        }
+    }
+
+    for (key, value) in overrides {
+        set_field(&mut fields, key, value.clone())?;
+    }
+
+    serde_json::from_value(Value::Object(fields)).map_err(|e| MjError::Import(e.to_string()))
+}
+
+fn set_field(fields: &mut Map<String, Value>, key: &str, value: String) -> Result<(), MjError> {
+    match fields.get_mut(key) {
+        Some(slot) => {
+            *slot = Value::String(value);
+            Ok(())
+        }
+        None => Err(MjError::Import(format!(
+            "unknown spec field '{}' (expected one of: {})",
+            key,
+            fields.keys().cloned().collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
+
+/// clap parser for `--spec key=value`.
+pub fn parse_spec_override(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pts");
+
+    #[test]
+    fn run_ids_follow_the_result_file() {
+        let id = |name: &str| {
+            let source = format!("{}/{}", FIXTURES, name);
+            import(&source, &pts_results::default_columns(), "UTC").unwrap().metadata.run_id
+        };
+
+        let core = id("mohamed-core-composite.xml");
+        assert_eq!(core, id("mohamed-core-composite.xml"));
+        assert_ne!(core, id("merged-composite.xml"));
+        assert!(uuid::Uuid::parse_str(&core).is_ok());
+    }
+}
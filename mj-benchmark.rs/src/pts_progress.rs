@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Progress tracking from `phoronix-test-suite batch-benchmark` output.
+//!
+//! PTS prints, per test run:
+//!
+//! ```text
+//! Flexible IO Tester 3.29:
+//!     pts/fio-2.1.0 [Type: Random Read - Engine: IO_uring - ... - Block Size: 4KB]
+//!     Test 7 of 24
+//!     Estimated Trial Run Count:    3
+//!         Started Run 1 @ 10:11:12
+//! ```
+
+/// Where a running suite is, as far as its output tells.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PtsProgress {
+    /// 1-based index of the running test and the number in the suite.
+    pub test_index: usize,
+    pub test_count: usize,
+    /// e.g. "Flexible IO Tester 3.29"
+    pub title: String,
+    /// e.g. "pts/fio-2.1.0"
+    pub test: String,
+    /// The option set, e.g. "Type: Random Read - … - Block Size: 4KB".
+    pub sub_test: Option<String>,
+    /// 1-based trial of the running test, 0 before the first starts.
+    pub trial: usize,
+    pub trials: Option<usize>,
+}
+
+impl PtsProgress {
+    /// Share of the suite done, counting finished trials of the running
+    /// test.
+    pub fn fraction(&self) -> f64 {
+        if self.test_count == 0 {
+            return 0.0;
+        }
+        let within = match self.trials {
+            Some(n) if n > 0 => self.trial.saturating_sub(1) as f64 / n as f64,
+            _ => 0.0,
+        };
+        ((self.test_index.saturating_sub(1) as f64 + within.min(1.0)) / self.test_count as f64)
+            .clamp(0.0, 1.0)
+    }
+
+    pub fn percent(&self) -> u16 {
+        (self.fraction() * 100.0).round() as u16
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    progress: PtsProgress,
+    last_line: String,
+}
+
+impl ProgressParser {
+    pub fn progress(&self) -> &PtsProgress {
+        &self.progress
+    }
+
+    /// Feed one output line; true when the progress changed.
+    pub fn feed(&mut self, line: &str) -> bool {
+        let line = strip_ansi(line);
+        let line = line.trim();
+        let p = &mut self.progress;
+
+        let changed = if let Some((index, count)) = parse_test_of(line) {
+            p.test_index = index;
+            p.test_count = count;
+            p.trial = 0;
+            true
+        } else if is_test_identifier(line) {
+            let (test, rest) = line.split_once(' ').unwrap_or((line, ""));
+            p.test = test.to_string();
+            p.sub_test = rest
+                .trim()
+                .strip_prefix('[')
+                .and_then(|r| r.strip_suffix(']'))
+                .map(str::to_string);
+            if let Some(title) = self.last_line.strip_suffix(':') {
+                p.title = title.to_string();
+            }
+            p.trial = 0;
+            p.trials = None;
+            true
+        } else if let Some(n) = line
+            .strip_prefix("Estimated Trial Run Count:")
+            .and_then(|n| n.trim().parse().ok())
+        {
+            p.trials = Some(n);
+            true
+        } else if let Some(n) = line
+            .strip_prefix("Started Run ")
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|n| n.parse().ok())
+        {
+            p.trial = n;
+            true
+        } else {
+            false
+        };
+
+        if !line.is_empty() {
+            self.last_line = line.to_string();
+        }
+        changed
+    }
+}
+
+/// "Test 7 of 24"
+fn parse_test_of(line: &str) -> Option<(usize, usize)> {
+    let (index, count) = line.strip_prefix("Test ")?.split_once(" of ")?;
+    Some((index.trim().parse().ok()?, count.trim().parse().ok()?))
+}
+
+/// "pts/fio-2.1.0 [...]", "local/my-test-1.0.0"
+fn is_test_identifier(line: &str) -> bool {
+    let first = line.split_whitespace().next().unwrap_or_default();
+    ["pts/", "local/", "system/"]
+        .iter()
+        .any(|repo| first.len() > repo.len() && first.starts_with(repo))
+}
+
+/// Drop terminal colour codes PTS adds on some setups.
+pub fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip "ESC [ … letter".
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+Flexible IO Tester 3.29:
+    pts/fio-2.1.0 [Type: Random Read - Engine: IO_uring - Buffered: No - Direct: Yes - Block Size: 4KB - Disk Target: Default Test Directory]
+    Test 7 of 24
+    Estimated Trial Run Count:    3
+    Estimated Test Run-Time:      2 Minutes
+        Started Run 1 @ 10:11:12
+        Started Run 2 @ 10:11:52";
+
+    fn feed_all(text: &str) -> ProgressParser {
+        let mut parser = ProgressParser::default();
+        for line in text.lines() {
+            parser.feed(line);
+        }
+        parser
+    }
+
+    #[test]
+    fn tracks_test_sub_test_and_trial() {
+        let parser = feed_all(OUTPUT);
+        let p = parser.progress();
+        assert_eq!(p.title, "Flexible IO Tester 3.29");
+        assert_eq!(p.test, "pts/fio-2.1.0");
+        assert!(p
+            .sub_test
+            .as_deref()
+            .unwrap()
+            .ends_with("Block Size: 4KB - Disk Target: Default Test Directory"));
+        assert_eq!((p.test_index, p.test_count), (7, 24));
+        assert_eq!(p.trials, Some(3));
+        assert_eq!(p.trial, 2);
+    }
+
+    #[test]
+    fn fraction_counts_finished_trials() {
+        let p = PtsProgress {
+            test_index: 2,
+            test_count: 4,
+            trial: 3,
+            trials: Some(3),
+            ..PtsProgress::default()
+        };
+        // One test done, two of three trials of the second done.
+        assert!((p.fraction() - (1.0 + 2.0 / 3.0) / 4.0).abs() < 1e-9);
+        assert_eq!(PtsProgress::default().percent(), 0);
+    }
+
+    #[test]
+    fn unrelated_lines_do_not_change_progress() {
+        let mut parser = ProgressParser::default();
+        assert!(!parser.feed("    Estimated Time To Completion: 41 Minutes"));
+        assert!(!parser.feed("    Test: Compression Rating:"));
+        assert!(!parser.feed("    Average: 98765 MIPS"));
+        assert_eq!(parser.progress(), &PtsProgress::default());
+    }
+
+    #[test]
+    fn colour_codes_are_ignored() {
+        let mut parser = ProgressParser::default();
+        assert!(parser.feed("\u{1b}[1;34m    Test 3 of 6\u{1b}[0m"));
+        assert_eq!(parser.progress().test_index, 3);
+    }
+}
@@ -0,0 +1,847 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Parsing of PTS `composite.xml` result files.
+//!
+//! A test profile can emit several `<Result>` blocks (one per option set,
+//! and fio emits both MB/s and IOPS for each), so a column is matched on
+//! the test identifier, a `<Description>` fragment and the `<Scale>`,
+//! never on the first place a name happens to appear. The mappings are
+//! data ([`PtsColumn`]), so a profile can add columns for other tests.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{BenchResults, CustomMetric, FioSetup, MetricStats, PtsSource};
+use crate::pts::PtsError;
+
+/// One `<Result>` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PtsResult {
+    /// Versioned identifier, e.g. "pts/fio-2.1.0".
+    pub identifier: String,
+    pub title: String,
+    pub arguments: String,
+    pub description: String,
+    pub scale: String,
+    pub value: Option<f64>,
+    /// Per-trial values from `<RawString>`, in trial order.
+    pub raw: Vec<f64>,
+    /// Why `value`/`raw` are not numbers (e.g. a graph's point list), in
+    /// which case both are empty. Only an error for a column mapping this
+    /// result.
+    pub invalid: Option<String>,
+}
+
+impl PtsResult {
+    /// Identifier without the profile version, e.g. "pts/fio".
+    pub fn test(&self) -> &str {
+        base_identifier(&self.identifier)
+    }
+
+    /// Value of option `name` in a "Key: Value - Key: Value" description.
+    /// Older fio profiles say "IO Engine" where newer ones say "Engine";
+    /// `Engine` matches both.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.description.split(" - ").find_map(|part| {
+            let (key, value) = part.split_once(": ")?;
+            let key = key.trim();
+            (key == name || key.strip_prefix("IO ") == Some(name)).then(|| value.trim())
+        })
+    }
+}
+
+/// Strip a trailing "-<version>" from a test identifier.
+pub fn base_identifier(identifier: &str) -> &str {
+    match identifier.rsplit_once('-') {
+        Some((base, version))
+            if version.starts_with(|c: char| c.is_ascii_digit())
+                && version.chars().all(|c| c.is_ascii_digit() || c == '.') =>
+        {
+            base
+        }
+        _ => identifier,
+    }
+}
+
+/// Results recorded by `system` (a `<System>` identifier; the first
+/// system when `None`). Merged result files hold an `<Entry>` per system,
+/// in no fixed order, and a system may have none for some results.
+pub fn parse_composite(xml: &str, system: Option<&str>) -> Result<Vec<PtsResult>, PtsError> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| PtsError::ParseError(format!("invalid composite.xml: {}", e)))?;
+    let root = doc.root_element();
+
+    let systems: Vec<String> = root
+        .children()
+        .filter(|n| n.has_tag_name("System"))
+        .map(|n| child_text(n, "Identifier"))
+        .collect();
+    let system = match system {
+        Some(id) if systems.iter().any(|s| s == id) => Some(id.to_string()),
+        Some(id) => {
+            return Err(PtsError::ParseError(format!(
+                "composite.xml has no system '{}' (it has: {})",
+                id,
+                systems.join(", ")
+            )))
+        }
+        None => systems.into_iter().next(),
+    };
+
+    let mut results = Vec::new();
+    for node in root.children().filter(|n| n.has_tag_name("Result")) {
+        let identifier = child_text(node, "Identifier");
+        let entry = node
+            .children()
+            .find(|n| n.has_tag_name("Data"))
+            .and_then(|data| data.children().find(|n| is_entry_of(*n, system.as_deref())));
+        let value = entry
+            .map(|entry| child_text(entry, "Value"))
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<f64>()
+                    .map_err(|_| format!("{}: value '{}' is not a number", identifier, v))
+            })
+            .transpose();
+        let raw = entry
+            .map(|entry| parse_raw(&identifier, &child_text(entry, "RawString")))
+            .transpose();
+        let (value, raw, invalid) = match (value, raw) {
+            (Ok(value), Ok(raw)) => (value, raw.unwrap_or_default(), None),
+            (Err(e), _) | (_, Err(e)) => (None, Vec::new(), Some(e)),
+        };
+
+        results.push(PtsResult {
+            title: child_text(node, "Title"),
+            arguments: child_text(node, "Arguments"),
+            description: child_text(node, "Description"),
+            scale: child_text(node, "Scale"),
+            value,
+            raw,
+            invalid,
+            identifier,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Whether `node` is an `<Entry>` recorded by `system`; any entry when
+/// the file has no `<System>` block.
+fn is_entry_of(node: roxmltree::Node, system: Option<&str>) -> bool {
+    node.has_tag_name("Entry") && system.is_none_or(|id| child_text(node, "Identifier") == id)
+}
+
+/// The `<Generated>` and first `<System>` blocks: what, when and on
+/// which machine a result file was recorded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PtsRunInfo {
+    pub title: String,
+    /// e.g. "Phoronix Test Suite 10.8.4"
+    pub test_client: String,
+    /// "YYYY-MM-DD HH:MM:SS", machine-local time.
+    pub last_modified: String,
+    pub identifier: String,
+    pub hardware: String,
+    pub software: String,
+    pub timestamp: String,
+}
+
+impl PtsRunInfo {
+    /// Value of `name` in the "Key: Value, Key: Value" hardware or
+    /// software summary, e.g. `component("Kernel")`.
+    pub fn component(&self, name: &str) -> Option<&str> {
+        self.hardware
+            .split(", ")
+            .chain(self.software.split(", "))
+            .find_map(|part| {
+                let (key, value) = part.split_once(": ")?;
+                (key.trim() == name).then(|| value.trim())
+            })
+    }
+}
+
+pub fn parse_run_info(xml: &str) -> Result<PtsRunInfo, PtsError> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| PtsError::ParseError(format!("invalid composite.xml: {}", e)))?;
+    let root = doc.root_element();
+    let block = |tag| root.children().find(|n| n.has_tag_name(tag));
+    let text = |node: Option<roxmltree::Node>, tag| {
+        node.map(|n| child_text(n, tag)).unwrap_or_default()
+    };
+
+    let generated = block("Generated");
+    let system = block("System");
+    Ok(PtsRunInfo {
+        title: text(generated, "Title"),
+        test_client: text(generated, "TestClient"),
+        last_modified: text(generated, "LastModified"),
+        identifier: text(system, "Identifier"),
+        hardware: text(system, "Hardware"),
+        software: text(system, "Software"),
+        timestamp: text(system, "TimeStamp"),
+    })
+}
+
+/// `<RawString>` holds one value per trial, separated by ':'.
+fn parse_raw(identifier: &str, raw: &str) -> Result<Vec<f64>, String> {
+    raw.split(':')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            v.parse::<f64>()
+                .map_err(|_| format!("{}: raw value '{}' is not a number", identifier, v))
+        })
+        .collect()
+}
+
+fn child_text(node: roxmltree::Node, tag: &str) -> String {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Maps one PTS `<Result>` to an output column.
+///
+/// ```toml
+/// [[profiles.lab.pts_columns]]
+/// column = "x264 Bosphorus 4K"
+/// test = "pts/x264"
+/// description = "Video Input: Bosphorus 4K"
+/// scale = "Frames Per Second"
+/// unit = "fps"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PtsColumn {
+    /// A built-in metric key (e.g. `openssl_mb_s`) to re-map that column,
+    /// or the CSV label of a new column.
+    pub column: String,
+    /// Test profile, with or without version.
+    pub test: String,
+    /// Must appear in the result's `<Description>`.
+    #[serde(default)]
+    pub description: String,
+    /// Must appear in the result's `<Arguments>`.
+    #[serde(default)]
+    pub arguments: String,
+    /// Each must equal the option of that name in the `<Description>`,
+    /// e.g. `{ "Block Size" = "1MB", "Direct" = "Yes" }`.
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    /// Must equal the result's `<Scale>`.
+    pub scale: String,
+    #[serde(default)]
+    pub unit: String,
+    /// Multiplier from `scale` to `unit`.
+    #[serde(default = "default_factor")]
+    pub factor: f64,
+}
+
+fn default_factor() -> f64 {
+    1.0
+}
+
+impl PtsColumn {
+    fn matches(&self, r: &PtsResult) -> bool {
+        r.test() == base_identifier(&self.test)
+            && r.description.contains(&self.description)
+            && r.arguments.contains(&self.arguments)
+            && self.options.iter().all(|(k, v)| r.option(k) == Some(v.as_str()))
+            && r.scale == self.scale
+    }
+
+    /// The filters in one line, for errors and `--dry-run`.
+    pub fn filter(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.description.is_empty() {
+            parts.push(format!("'{}'", self.description));
+        }
+        if !self.arguments.is_empty() {
+            parts.push(format!("arguments '{}'", self.arguments));
+        }
+        parts.extend(self.options.iter().map(|(k, v)| format!("{}: {}", k, v)));
+        parts.push(format!("scale {}", self.scale));
+        parts.join(", ")
+    }
+}
+
+fn builtin(column: &str, test: &str, description: &str, scale: &str, factor: f64) -> PtsColumn {
+    PtsColumn {
+        column: column.into(),
+        test: test.into(),
+        description: description.into(),
+        arguments: String::new(),
+        options: BTreeMap::new(),
+        scale: scale.into(),
+        unit: String::new(),
+        factor,
+    }
+}
+
+/// fio option sets behind the built-in fio columns. Direct I/O keeps the
+/// page cache out of the numbers.
+pub const FIO_ENGINE: &str = "Linux AIO";
+pub const FIO_SEQ_BLOCK_SIZE: &str = "2MB";
+pub const FIO_RAND_BLOCK_SIZE: &str = "4KB";
+
+fn fio(column: &str, kind: &str, block_size: &str, scale: &str) -> PtsColumn {
+    let options = [
+        ("Type", kind),
+        ("Engine", FIO_ENGINE),
+        ("Direct", "Yes"),
+        ("Block Size", block_size),
+    ];
+    PtsColumn {
+        options: options
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        ..builtin(column, "pts/fio", "", scale, 1.0)
+    }
+}
+
+/// Where each built-in `BenchResults` PTS column comes from.
+pub fn default_columns() -> Vec<PtsColumn> {
+    vec![
+        builtin("seven_zip_mips", "pts/compress-7zip", "Test: Compression Rating", "MIPS", 1.0),
+        builtin("openssl_mb_s", "pts/openssl", "Algorithm: SHA256", "byte/s", 1e-6),
+        builtin("ramspeed_mb_s", "pts/ramspeed", "Type: Average - Benchmark: Integer", "MB/s", 1.0),
+        fio("fio_seq_read_mb_s", "Sequential Read", FIO_SEQ_BLOCK_SIZE, "MB/s"),
+        fio("fio_seq_write_mb_s", "Sequential Write", FIO_SEQ_BLOCK_SIZE, "MB/s"),
+        fio("fio_rand_read_iops", "Random Read", FIO_RAND_BLOCK_SIZE, "IOPS"),
+        fio("fio_rand_write_iops", "Random Write", FIO_RAND_BLOCK_SIZE, "IOPS"),
+        builtin("glmark2_score", "pts/glmark2", "Resolution:", "Score", 1.0),
+        builtin("kernel_build_time_s", "pts/build-linux-kernel", "", "Seconds", 1.0),
+    ]
+}
+
+/// The built-in mappings with `configured` layered on top: an entry
+/// naming a built-in key replaces its default, any other entry adds a
+/// column.
+pub fn effective_columns(configured: &[PtsColumn]) -> Vec<PtsColumn> {
+    let mut columns = default_columns();
+    for col in configured {
+        match columns.iter_mut().find(|c| c.column == col.column) {
+            Some(existing) => *existing = col.clone(),
+            None => columns.push(col.clone()),
+        }
+    }
+    columns
+}
+
+/// Custom columns from `columns` with no values, for rows where PTS
+/// did not run.
+pub fn empty_custom_metrics(columns: &[PtsColumn]) -> Vec<CustomMetric> {
+    let mut bench = BenchResults::default();
+    columns
+        .iter()
+        .filter(|c| bench.pts_metric_mut(&c.column).is_none())
+        .map(|c| CustomMetric {
+            label: c.column.clone(),
+            unit: c.unit.clone(),
+            value: None,
+        })
+        .collect()
+}
+
+/// Fill the columns in `columns` from `results`. Every column whose test
+/// is in `tests` must have a matching result; columns for tests that were
+/// not run stay empty. A column pinned to `options` (like the fio columns)
+/// whose option set did not run stays empty too, and its
+/// [`PtsError::MissingResult`] is returned as a warning. A matching result
+/// whose value is not a number is a [`PtsError::ParseError`].
+pub fn to_bench_results(
+    results: &[PtsResult],
+    tests: &[String],
+    columns: &[PtsColumn],
+) -> Result<(BenchResults, Vec<PtsError>), PtsError> {
+    let wanted = |col: &PtsColumn| {
+        tests
+            .iter()
+            .any(|t| base_identifier(t) == base_identifier(&col.test))
+    };
+    let (bench, missing) = map_columns(results, columns, wanted);
+    let pinned = |err: &PtsError| match err {
+        PtsError::MissingResult { column, .. } => columns
+            .iter()
+            .any(|c| c.column == *column && !c.options.is_empty()),
+        _ => false,
+    };
+    let (warnings, errors): (Vec<_>, Vec<_>) = missing.into_iter().partition(pinned);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok((bench, warnings)),
+    }
+}
+
+/// Like [`to_bench_results`] for result files from other runs
+/// (`import-pts`): the file decides which tests ran, and a column whose
+/// test is in the file but matches no result, or only one whose value is
+/// not a number, is left empty and returned as a [`PtsError::MissingResult`]
+/// or [`PtsError::ParseError`] instead of failing the import.
+pub fn import_bench_results(
+    results: &[PtsResult],
+    columns: &[PtsColumn],
+) -> (BenchResults, Vec<PtsError>) {
+    let wanted = |col: &PtsColumn| {
+        results
+            .iter()
+            .any(|r| r.test() == base_identifier(&col.test))
+    };
+    map_columns(results, columns, wanted)
+}
+
+fn map_columns(
+    results: &[PtsResult],
+    columns: &[PtsColumn],
+    wanted: impl Fn(&PtsColumn) -> bool,
+) -> (BenchResults, Vec<PtsError>) {
+    let mut bench = BenchResults {
+        notes: "PTS results parsed from composite.xml".into(),
+        pts_source: Some(PtsSource::Pts),
+        ..BenchResults::default()
+    };
+
+    let mut matched: Vec<(&str, &PtsResult)> = Vec::new();
+    let mut missing = Vec::new();
+
+    for col in columns {
+        let result = if wanted(col) {
+            let result = results
+                .iter()
+                .find(|r| col.matches(r) && (r.value.is_some() || r.invalid.is_some()));
+            match result {
+                None => {
+                    missing.push(PtsError::MissingResult {
+                        column: col.column.clone(),
+                        test: col.test.clone(),
+                        filter: col.filter(),
+                    });
+                    None
+                }
+                Some(PtsResult { invalid: Some(reason), .. }) => {
+                    missing.push(PtsError::ParseError(format!("{} (column {})", reason, col.column)));
+                    None
+                }
+                Some(result) => Some(result),
+            }
+        } else {
+            None
+        };
+
+        let value = result.and_then(|result| {
+            matched.push((col.column.as_str(), result));
+
+            // A result without per-trial values counts as one trial.
+            let samples = if result.raw.is_empty() {
+                result.value.into_iter().collect()
+            } else {
+                result.raw.clone()
+            };
+            let scaled = samples.into_iter().map(|v| v * col.factor).collect();
+            if let Some(stats) = MetricStats::from_samples(scaled) {
+                bench.stats.insert(col.column.clone(), stats);
+            }
+
+            result.value.map(|v| v * col.factor)
+        });
+
+        match bench.pts_metric_mut(&col.column) {
+            Some(slot) => *slot = value,
+            None => bench.custom.push(CustomMetric {
+                label: col.column.clone(),
+                unit: col.unit.clone(),
+                value,
+            }),
+        }
+    }
+
+    bench.fio = fio_setup(&matched);
+    (bench, missing)
+}
+
+fn fio_setup(matched: &[(&str, &PtsResult)]) -> Option<FioSetup> {
+    let find = |prefix: &str| {
+        matched
+            .iter()
+            .find(|(column, _)| column.starts_with(prefix))
+            .map(|(_, r)| *r)
+    };
+    let seq = find("fio_seq_");
+    let rand = find("fio_rand_");
+    if seq.is_none() && rand.is_none() {
+        return None;
+    }
+
+    let option = |r: Option<&PtsResult>, name| r.and_then(|r| r.option(name)).map(str::to_string);
+    Some(FioSetup {
+        seq_block_size: option(seq, "Block Size"),
+        rand_block_size: option(rand, "Block Size"),
+        test_path: option(seq.or(rand), "Disk Target"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORE: &str = include_str!("../tests/fixtures/pts/mohamed-core-composite.xml");
+    const NO_FIO: &str = include_str!("../tests/fixtures/pts/missing-fio-composite.xml");
+    const MERGED: &str = include_str!("../tests/fixtures/pts/merged-composite.xml");
+    const MONITOR: &str = include_str!("../tests/fixtures/pts/monitor-composite.xml");
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("value missing");
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    fn default_tests() -> Vec<String> {
+        crate::pts::DEFAULT_TESTS.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_every_result_block() {
+        let results = parse_composite(CORE, None).unwrap();
+        assert_eq!(results.len(), 18);
+
+        let first = &results[0];
+        assert_eq!(first.identifier, "pts/compress-7zip-1.10.0");
+        assert_eq!(first.test(), "pts/compress-7zip");
+        assert_eq!(first.title, "7-Zip Compression");
+        assert_eq!(first.description, "Test: Decompression Rating");
+        assert_eq!(first.scale, "MIPS");
+        assert_eq!(first.value, Some(87654.0));
+    }
+
+    #[test]
+    fn maps_each_column_to_its_own_option_set() {
+        let results = parse_composite(CORE, None).unwrap();
+        let (bench, _) = to_bench_results(&results, &default_tests(), &default_columns()).unwrap();
+
+        // Decompression and RSA results come first in the file and must
+        // not be picked up.
+        assert_eq!(bench.seven_zip_mips, Some(98765.0));
+        assert_close(bench.openssl_mb_s, 2345.678901);
+        assert_eq!(bench.ramspeed_mb_s, Some(21345.67));
+        assert_eq!(bench.fio_seq_read_mb_s, Some(3120.0));
+        assert_eq!(bench.fio_seq_write_mb_s, Some(2710.0));
+        assert_eq!(bench.fio_rand_read_iops, Some(412000.0));
+        assert_eq!(bench.fio_rand_write_iops, Some(298000.0));
+        assert_eq!(bench.glmark2_score, Some(4321.0));
+        assert_eq!(bench.kernel_build_time_s, Some(187.43));
+    }
+
+    #[test]
+    fn missing_fio_option_sets_leave_their_columns_empty() {
+        let results = parse_composite(NO_FIO, None).unwrap();
+        let (bench, warnings) =
+            to_bench_results(&results, &default_tests(), &default_columns()).unwrap();
+
+        assert_eq!(bench.fio_seq_read_mb_s, None);
+        assert_eq!(bench.fio_rand_write_iops, None);
+        assert_eq!(bench.seven_zip_mips, Some(98765.0));
+        assert!(bench.kernel_build_time_s.is_some());
+
+        assert_eq!(warnings.len(), 4);
+        match &warnings[0] {
+            PtsError::MissingResult { column, test, filter } => {
+                assert_eq!(column, "fio_seq_read_mb_s");
+                assert_eq!(test, "pts/fio");
+                assert!(filter.contains("Block Size: 2MB"), "{}", filter);
+            }
+            other => panic!("expected MissingResult, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_expected_result_is_an_error() {
+        let results = parse_composite(CORE, None).unwrap();
+        let mut columns = default_columns();
+        columns.push(builtin("OpenSSL RSA8192", "pts/openssl", "Algorithm: RSA8192", "sign/s", 1.0));
+        match to_bench_results(&results, &default_tests(), &columns) {
+            Err(PtsError::MissingResult { column, .. }) => assert_eq!(column, "OpenSSL RSA8192"),
+            other => panic!("expected MissingResult, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tests_not_in_the_suite_are_not_required() {
+        let results = parse_composite(NO_FIO, None).unwrap();
+        let tests: Vec<String> = default_tests()
+            .into_iter()
+            .filter(|t| t != "pts/fio")
+            .collect();
+        let (bench, _) = to_bench_results(&results, &tests, &default_columns()).unwrap();
+        assert_eq!(bench.fio_seq_read_mb_s, None);
+        assert_eq!(bench.fio, None);
+        assert_eq!(bench.seven_zip_mips, Some(98765.0));
+    }
+
+    #[test]
+    fn versioned_test_names_match() {
+        let results = parse_composite(CORE, None).unwrap();
+        let tests = ["pts/openssl-3.1.0".to_string()];
+        let (bench, _) = to_bench_results(&results, &tests, &default_columns()).unwrap();
+        assert_close(bench.openssl_mb_s, 2345.678901);
+        assert_eq!(bench.seven_zip_mips, None);
+    }
+
+    #[test]
+    fn malformed_xml_is_a_parse_error() {
+        assert!(matches!(
+            parse_composite("<PhoronixTestSuite><Result>", None),
+            Err(PtsError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn unmapped_results_may_have_non_numeric_values() {
+        let results = parse_composite(MONITOR, None).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].value, None);
+        assert!(results[2].invalid.as_deref().unwrap().contains("'45.1,47.3"));
+
+        let tests = ["pts/compress-7zip".to_string(), "pts/openssl".to_string()];
+        let (bench, warnings) = to_bench_results(&results, &tests, &default_columns()).unwrap();
+        assert_eq!(bench.seven_zip_mips, Some(98765.0));
+        assert_close(bench.openssl_mb_s, 2345.678901);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(import_bench_results(&results, &default_columns()).1.is_empty());
+    }
+
+    #[test]
+    fn mapped_non_numeric_value_is_an_error() {
+        let xml = MONITOR.replace("<Value>98765</Value>", "<Value>n/a</Value>");
+        let results = parse_composite(&xml, None).unwrap();
+        let tests = ["pts/compress-7zip".to_string(), "pts/openssl".to_string()];
+        match to_bench_results(&results, &tests, &default_columns()) {
+            Err(PtsError::ParseError(msg)) => {
+                assert!(msg.contains("'n/a'") && msg.contains("seven_zip_mips"), "{}", msg)
+            }
+            other => panic!("expected ParseError, got {:?}", other),
+        }
+
+        let (bench, warnings) = import_bench_results(&results, &default_columns());
+        assert_eq!(bench.seven_zip_mips, None);
+        assert_close(bench.openssl_mb_s, 2345.678901);
+        assert!(matches!(warnings.as_slice(), [PtsError::ParseError(_)]), "{:?}", warnings);
+    }
+
+    #[test]
+    fn raw_trials_become_statistics() {
+        let results = parse_composite(CORE, None).unwrap();
+        let (bench, _) = to_bench_results(&results, &default_tests(), &default_columns()).unwrap();
+
+        let s = &bench.stats["seven_zip_mips"];
+        assert_eq!(s.trials, 3);
+        assert_eq!(s.raw, vec![98123.0, 99011.0, 99161.0]);
+        assert_eq!(s.min, 98123.0);
+        assert_eq!(s.max, 99161.0);
+        assert!((s.mean - 98765.0).abs() < 1e-9);
+        assert!((s.std_dev - 561.024064).abs() < 1e-6, "{}", s.std_dev);
+
+        // Scaled into the column's unit like the value itself.
+        let s = &bench.stats["openssl_mb_s"];
+        assert!((s.min - 2341.0).abs() < 1e-6);
+
+        let metric = bench.metrics().into_iter().find(|m| m.key == "glmark2_score").unwrap();
+        assert_eq!(metric.stats.unwrap().trials, 3);
+    }
+
+    #[test]
+    fn single_value_without_raw_string_is_one_trial() {
+        let xml = CORE.replacen("<RawString>98123:99011:99161</RawString>", "<RawString/>", 1);
+        let results = parse_composite(&xml, None).unwrap();
+        let (bench, _) = to_bench_results(&results, &default_tests(), &default_columns()).unwrap();
+
+        let s = &bench.stats["seven_zip_mips"];
+        assert_eq!(s.trials, 1);
+        assert_eq!(s.std_dev, 0.0);
+        assert_eq!(s.raw, vec![98765.0]);
+    }
+
+    #[test]
+    fn fio_columns_skip_other_option_sets() {
+        // The fixture lists 4KB, buffered and io_uring sequential/random
+        // reads before the pinned 2MB / 4KB direct Linux AIO ones.
+        let results = parse_composite(CORE, None).unwrap();
+        let (bench, _) = to_bench_results(&results, &default_tests(), &default_columns()).unwrap();
+
+        assert_eq!(bench.fio_seq_read_mb_s, Some(3120.0));
+        assert_eq!(bench.fio_rand_read_iops, Some(412000.0));
+        assert_eq!(
+            bench.fio,
+            Some(FioSetup {
+                seq_block_size: Some("2MB".into()),
+                rand_block_size: Some("4KB".into()),
+                test_path: Some("/mnt/nvme0".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn fio_options_can_be_overridden() {
+        let results = parse_composite(CORE, None).unwrap();
+        let seq_4k = fio("fio_seq_read_mb_s", "Sequential Read", "4KB", "MB/s");
+        let columns = effective_columns(&[seq_4k]);
+        let (bench, _) = to_bench_results(&results, &default_tests(), &columns).unwrap();
+
+        assert_eq!(bench.fio_seq_read_mb_s, Some(410.0));
+        assert_eq!(bench.fio.unwrap().seq_block_size.as_deref(), Some("4KB"));
+    }
+
+    #[test]
+    fn description_options_parse_with_engine_alias() {
+        let results = parse_composite(CORE, None).unwrap();
+        let r = results.iter().find(|r| r.test() == "pts/fio").unwrap();
+        assert_eq!(r.option("Type"), Some("Sequential Read"));
+        assert_eq!(r.option("Engine"), Some("Linux AIO"));
+        assert_eq!(r.option("IO Engine"), Some("Linux AIO"));
+        assert_eq!(r.option("Disk Target"), Some("/mnt/nvme0"));
+        assert_eq!(r.option("Missing"), None);
+    }
+
+    fn rsa_column() -> PtsColumn {
+        PtsColumn {
+            column: "OpenSSL RSA4096".into(),
+            test: "pts/openssl".into(),
+            description: "Algorithm: RSA4096".into(),
+            arguments: String::new(),
+            options: BTreeMap::new(),
+            scale: "sign/s".into(),
+            unit: "sign/s".into(),
+            factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn configured_columns_add_custom_metrics() {
+        let results = parse_composite(CORE, None).unwrap();
+        let columns = effective_columns(&[rsa_column()]);
+        let (bench, _) = to_bench_results(&results, &default_tests(), &columns).unwrap();
+
+        assert_eq!(bench.custom.len(), 1);
+        assert_eq!(bench.custom[0].label, "OpenSSL RSA4096");
+        assert_eq!(bench.custom[0].unit, "sign/s");
+        assert_eq!(bench.custom[0].value, Some(3456.7));
+
+        let last = bench.metrics().pop().unwrap();
+        assert_eq!(last.label, "OpenSSL RSA4096");
+    }
+
+    #[test]
+    fn configured_columns_replace_builtin_mappings() {
+        let results = parse_composite(CORE, None).unwrap();
+        let sha512 = PtsColumn {
+            description: "Algorithm: SHA512".into(),
+            ..builtin("openssl_mb_s", "pts/openssl", "", "byte/s", 1e-6)
+        };
+        let columns = effective_columns(&[sha512]);
+        assert_eq!(columns.len(), default_columns().len());
+
+        let (bench, _) = to_bench_results(&results, &default_tests(), &columns).unwrap();
+        assert_close(bench.openssl_mb_s, 1800.0);
+        assert!(bench.custom.is_empty());
+    }
+
+    #[test]
+    fn arguments_filter_narrows_the_match() {
+        let results = parse_composite(CORE, None).unwrap();
+        let col = PtsColumn {
+            arguments: "AVERAGE".into(),
+            ..builtin("RAMspeed Avg", "pts/ramspeed", "", "MB/s", 1.0)
+        };
+        let (bench, _) = to_bench_results(&results, &default_tests(), &[col]).unwrap();
+        assert_eq!(bench.custom[0].value, Some(21345.67));
+    }
+
+    #[test]
+    fn custom_column_for_test_not_run_is_empty() {
+        let results = parse_composite(CORE, None).unwrap();
+        let x264 = PtsColumn {
+            unit: "fps".into(),
+            ..builtin("x264 4K", "pts/x264", "Video Input: Bosphorus 4K", "Frames Per Second", 1.0)
+        };
+        let (bench, _) = to_bench_results(&results, &default_tests(), &[x264]).unwrap();
+        assert_eq!(bench.custom[0].value, None);
+    }
+
+    #[test]
+    fn import_leaves_unmatched_columns_empty() {
+        let results = parse_composite(CORE, None).unwrap();
+        let mut columns = default_columns();
+        columns.push(PtsColumn {
+            scale: "ops/s".into(),
+            ..rsa_column()
+        });
+        let (bench, missing) = import_bench_results(&results, &columns);
+
+        assert_eq!(bench.seven_zip_mips, Some(98765.0));
+        assert_eq!(bench.fio_rand_read_iops, Some(412000.0));
+        assert_eq!(missing.len(), 1);
+        assert!(matches!(&missing[0], PtsError::MissingResult { column, .. } if column == "OpenSSL RSA4096"));
+        assert_eq!(bench.custom[0].value, None);
+    }
+
+    #[test]
+    fn import_only_expects_tests_in_the_file() {
+        let results = parse_composite(NO_FIO, None).unwrap();
+        let (bench, missing) = import_bench_results(&results, &default_columns());
+        assert!(missing.is_empty(), "{:?}", missing);
+        assert_eq!(bench.fio_seq_read_mb_s, None);
+        assert!(bench.kernel_build_time_s.is_some());
+    }
+
+    #[test]
+    fn merged_files_yield_one_systems_entries() {
+        let value = |results: &[PtsResult], test: &str| {
+            results.iter().find(|r| r.test() == test).and_then(|r| r.value)
+        };
+
+        let first = parse_composite(MERGED, None).unwrap();
+        assert_close(value(&first, "pts/compress-7zip"), 98765.0);
+        assert_eq!(value(&first, "pts/openssl"), None);
+        assert_close(value(&first, "pts/build-linux-kernel"), 98.76);
+        assert_eq!(first[0].raw, vec![98123.0, 99011.0, 99161.0]);
+
+        let second = parse_composite(MERGED, Some("ThinkPad X13")).unwrap();
+        assert_close(value(&second, "pts/compress-7zip"), 61234.0);
+        assert_close(value(&second, "pts/openssl"), 1987654321.0);
+        assert_eq!(value(&second, "pts/build-linux-kernel"), None);
+
+        assert!(parse_composite(MERGED, Some("Desktop")).is_err());
+    }
+
+    #[test]
+    fn run_info_comes_from_generated_and_system() {
+        let info = parse_run_info(CORE).unwrap();
+        assert_eq!(info.title, "mohamed-core");
+        assert_eq!(info.test_client, "Phoronix Test Suite 10.8.4");
+        assert_eq!(info.timestamp, "2024-05-01 12:00:03");
+        assert_eq!(info.component("Kernel"), Some("6.1.0-20-amd64 (x86_64)"));
+        assert_eq!(info.component("Memory"), Some("32GB"));
+        assert_eq!(info.component("Graphics"), None);
+    }
+
+    #[test]
+    fn base_identifier_strips_only_versions() {
+        assert_eq!(base_identifier("pts/fio-2.1.0"), "pts/fio");
+        assert_eq!(base_identifier("pts/build-linux-kernel-1.15.0"), "pts/build-linux-kernel");
+        assert_eq!(base_identifier("pts/build-linux-kernel"), "pts/build-linux-kernel");
+        assert_eq!(base_identifier("pts/compress-7zip"), "pts/compress-7zip");
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Machine-readable run report (`run --output-json <path>`).
+//!
+//! Dashboards read this instead of re-parsing the CSV header, so field
+//! names here are a stable interface: add, don't rename.
+
+use std::fs;
+
+use serde::Serialize;
+
+use crate::error::MjError;
+use crate::metadata::RunMetadata;
+use crate::model::{BrowserScores, DeviceSpecs, FioSetup, Metric};
+use crate::pipeline::{PipelineStep, RunSummary, StepOutcome};
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub run_id: String,
+    pub metadata: RunMetadata,
+    pub succeeded: bool,
+    /// Process exit code of the run; see `error.rs`.
+    pub exit_code: u8,
+    pub specs: Option<DeviceSpecs>,
+    pub metrics: Vec<Metric>,
+    /// Browser suite scores per configured browser.
+    pub browsers: Vec<BrowserScores>,
+    pub fio: Option<FioSetup>,
+    pub notes: String,
+    pub steps: Vec<StepReport>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepReport {
+    pub step: PipelineStep,
+    pub status: &'static str,
+    pub duration_s: Option<f64>,
+    pub message: Option<String>,
+}
+
+impl RunReport {
+    pub fn from_summary(summary: &RunSummary) -> Self {
+        let steps: Vec<StepReport> = summary
+            .outcomes
+            .iter()
+            .map(|(step, outcome)| {
+                let (status, duration_s, message) = match outcome {
+                    StepOutcome::Completed(d) => ("completed", Some(d.as_secs_f64()), None),
+                    StepOutcome::Restored => ("restored", None, None),
+                    StepOutcome::Skipped(reason) => ("skipped", None, Some(reason.clone())),
+                    StepOutcome::Failed(err) => ("failed", None, Some(err.to_string())),
+                    StepOutcome::Cancelled => ("cancelled", None, None),
+                };
+                StepReport { step: *step, status, duration_s, message }
+            })
+            .collect();
+
+        let errors = summary
+            .failure()
+            .map(|(step, err)| vec![format!("{}: {} ({})", step.label(), err, err.hint())])
+            .unwrap_or_default();
+
+        let bench = summary.bench.clone().unwrap_or_default();
+
+        Self {
+            run_id: summary.metadata.run_id.clone(),
+            metadata: summary.metadata.clone(),
+            succeeded: summary.succeeded(),
+            exit_code: match summary.failure() {
+                Some((_, err)) => err.exit_code(),
+                None if summary.cancelled().is_some() => MjError::Cancelled.exit_code(),
+                None => 0,
+            },
+            specs: summary.specs.clone(),
+            metrics: bench.metrics(),
+            browsers: bench.browsers.clone(),
+            fio: bench.fio.clone(),
+            notes: bench.notes,
+            steps,
+            errors,
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), std::io::Error> {
+        let data = serde_json::to_string_pretty(self)
+            .map_err(std::io::Error::other)?;
+        fs::write(path, data)
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! `mj-benchmark specs`: show what `collect_specs` would put in the CSV.
+
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::model::DeviceSpecs;
+
+const UNKNOWN_MARKER: &str = "⚠ (empty/unknown)";
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SpecsFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+pub fn print_specs(specs: &DeviceSpecs, format: SpecsFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        SpecsFormat::Table => print_table(specs),
+        SpecsFormat::Json => print_json(specs)?,
+        SpecsFormat::Csv => print_csv(specs)?,
+    }
+    Ok(())
+}
+
+fn print_table(specs: &DeviceSpecs) {
+    let fields = specs.fields();
+    let width = fields.iter().map(|(_, label, _)| label.len()).max().unwrap_or(0);
+    let mut unknown = 0;
+
+    for (_, label, value) in &fields {
+        if DeviceSpecs::is_unknown(value) {
+            unknown += 1;
+            println!("{:<width$}  {}", label, marked(value), width = width);
+            continue;
+        }
+
+        // Multi-line collector output (lsblk, xrandr, …) stays aligned.
+        let mut lines = value.trim().lines();
+        println!("{:<width$}  {}", label, lines.next().unwrap_or_default(), width = width);
+        for line in lines {
+            println!("{:<width$}  {}", "", line, width = width);
+        }
+    }
+
+    println!();
+    println!("{} of {} fields empty or unknown", unknown, fields.len());
+}
+
+fn print_json(specs: &DeviceSpecs) -> Result<(), serde_json::Error> {
+    let missing: Vec<&str> = specs
+        .fields()
+        .into_iter()
+        .filter(|(_, _, value)| DeviceSpecs::is_unknown(value))
+        .map(|(key, _, _)| key)
+        .collect();
+
+    let doc = json!({
+        "specs": specs,
+        "missing": missing,
+    });
+    println!("{}", serde_json::to_string_pretty(&doc)?);
+    Ok(())
+}
+
+fn print_csv(specs: &DeviceSpecs) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = specs.fields();
+    let mut wtr = csv::Writer::from_writer(std::io::stdout());
+    wtr.write_record(fields.iter().map(|(_, label, _)| *label))?;
+    wtr.write_record(fields.iter().map(|(_, _, value)| {
+        if DeviceSpecs::is_unknown(value) {
+            marked(value)
+        } else {
+            value.to_string()
+        }
+    }))?;
+    wtr.flush()?;
+    Ok(())
+}
+
+/// An unknown value as shown to the user: the marker, after whatever the
+/// collector returned.
+fn marked(value: &str) -> String {
+    if value.trim().is_empty() {
+        UNKNOWN_MARKER.to_string()
+    } else {
+        format!("{} {}", value.trim(), UNKNOWN_MARKER)
+    }
+}
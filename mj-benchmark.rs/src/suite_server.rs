@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! Local copies of the browser suites, served over HTTP on localhost.
+//!
+//! With `browser_suites = "<dir>"` the browser step serves `<dir>` on a
+//! free 127.0.0.1 port and opens the suites there instead of on
+//! browserbench.org. Each suite lives in the subdirectory its `path`
+//! starts with, named like on browserbench.org (`Speedometer2.1/`,
+//! `JetStream2.2/`, `MotionMark1.3/`, `Speedometer3.1/`), optionally with a
+//! `VERSION` file whose first line says which upstream revision the copy
+//! was taken from. Suites whose `path` is a full URL are not served.
+//! Symlinks in the copy are followed only as long as they stay inside it.
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::browser_bench::{BrowserError, BrowserSuite};
+
+/// Largest request head read before the connection is dropped.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// First and longest wait before accepting again after running out of
+/// file descriptors or memory.
+const ACCEPT_BACKOFF: (Duration, Duration) = (Duration::from_millis(10), Duration::from_secs(1));
+
+/// A static file server on 127.0.0.1; stops when dropped.
+pub struct SuiteServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl SuiteServer {
+    /// Serve `root` on a free local port. Fails if a suite in `suites`
+    /// has no start page there.
+    pub async fn start(root: &Path, suites: &[BrowserSuite]) -> Result<Self, BrowserError> {
+        let failed = |reason: String| BrowserError::LocalSuites(root.display().to_string(), reason);
+
+        let missing: Vec<&str> = suites
+            .iter()
+            .filter(|s| !s.is_absolute() && !start_page(root, s).is_some_and(|p| p.is_file()))
+            .map(|s| s.path.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(failed(format!("no start page for {}", missing.join(", "))));
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| failed(format!("cannot listen on 127.0.0.1: {}", e)))?;
+        let addr = listener.local_addr().map_err(|e| failed(e.to_string()))?;
+
+        // Served files are checked against the real root, symlinks resolved.
+        let root = root
+            .canonicalize()
+            .map_err(|e| failed(format!("cannot resolve the directory: {}", e)))?;
+        let shared = Arc::new(root);
+        let task = tokio::spawn(async move {
+            let mut backoff = ACCEPT_BACKOFF.0;
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    // The peer gave up before the connection was accepted.
+                    Err(e) if is_per_connection(&e) => continue,
+                    // Connections still being served hold descriptors.
+                    Err(e) if is_out_of_resources(&e) => {
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(ACCEPT_BACKOFF.1);
+                        continue;
+                    }
+                    // The listener is unusable; suites then fail to load.
+                    Err(_) => break,
+                };
+                backoff = ACCEPT_BACKOFF.0;
+                let root = shared.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, &root).await;
+                });
+            }
+        });
+
+        Ok(Self { addr, task })
+    }
+
+    /// Base URL the suite paths are appended to, with a trailing slash.
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+}
+
+impl Drop for SuiteServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn is_per_connection(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset | io::ErrorKind::Interrupted
+    )
+}
+
+fn is_out_of_resources(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if let Some(code) = e.raw_os_error() {
+        return [libc::EMFILE, libc::ENFILE, libc::ENOBUFS, libc::ENOMEM].contains(&code);
+    }
+    e.kind() == io::ErrorKind::OutOfMemory
+}
+
+/// First line of the suite's `VERSION` file, or its nominal version, with
+/// "(local)" appended.
+pub fn suite_version(root: &Path, suite: &BrowserSuite) -> String {
+    let pinned = suite
+        .dir()
+        .and_then(|dir| std::fs::read_to_string(root.join(dir).join("VERSION")).ok())
+        .and_then(|s| s.lines().next().map(|l| l.trim().to_string()))
+        .filter(|l| !l.is_empty());
+    format!("{} (local)", pinned.as_deref().unwrap_or(&suite.version))
+}
+
+/// The file a relative suite `path` opens under `root`.
+fn start_page(root: &Path, suite: &BrowserSuite) -> Option<PathBuf> {
+    let path = suite.path.split(['?', '#']).next()?;
+    let file = resolve(root, path)?;
+    Some(if path.ends_with('/') { file.join("index.html") } else { file })
+}
+
+/// Answer one request on `stream`, then close it.
+async fn serve(mut stream: TcpStream, root: &Path) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 2048];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || head.len() > MAX_REQUEST_HEAD {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or("/"));
+
+    let response = match method {
+        "GET" | "HEAD" => respond(root, target).await,
+        _ => Response::status(405, "Method Not Allowed"),
+    };
+    stream.write_all(&response.head()).await?;
+    if method == "GET" {
+        stream.write_all(&response.body).await?;
+    }
+    stream.shutdown().await
+}
+
+struct Response {
+    code: u16,
+    reason: &'static str,
+    content_type: &'static str,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn status(code: u16, reason: &'static str) -> Self {
+        Self {
+            code,
+            reason,
+            content_type: "text/plain; charset=utf-8",
+            location: None,
+            body: reason.as_bytes().to_vec(),
+        }
+    }
+
+    fn head(&self) -> Vec<u8> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+            self.code,
+            self.reason,
+            self.content_type,
+            self.body.len()
+        );
+        if let Some(location) = &self.location {
+            head.push_str(&format!("Location: {}\r\n", location));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+}
+
+/// `root` is canonical; a file whose real path is outside it, through a
+/// symlink, is not found.
+async fn respond(root: &Path, target: &str) -> Response {
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let Some(file) = resolve(root, path) else {
+        return Response::status(404, "Not Found");
+    };
+    let file = match tokio::fs::canonicalize(&file).await {
+        Ok(file) if file.starts_with(root) => file,
+        _ => return Response::status(404, "Not Found"),
+    };
+
+    // Relative links in a suite's index.html need the trailing slash.
+    if file.is_dir() {
+        if !path.ends_with('/') {
+            return Response {
+                location: Some(format!("{}/", path)),
+                ..Response::status(301, "Moved Permanently")
+            };
+        }
+        return match tokio::fs::read(file.join("index.html")).await {
+            Ok(body) => Response {
+                content_type: content_type(Path::new("index.html")),
+                body,
+                ..Response::status(200, "OK")
+            },
+            Err(_) => Response::status(404, "Not Found"),
+        };
+    }
+
+    match tokio::fs::read(&file).await {
+        Ok(body) => Response {
+            content_type: content_type(&file),
+            body,
+            ..Response::status(200, "OK")
+        },
+        Err(_) => Response::status(404, "Not Found"),
+    }
+}
+
+/// The file under `root` for a URL path, or `None` if the path would
+/// leave `root`.
+fn resolve(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path)?;
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    let mut path = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_stay_under_the_root() {
+        let root = Path::new("/srv/suites");
+        assert_eq!(
+            resolve(root, "/Speedometer2.1/resources/main.js"),
+            Some(root.join("Speedometer2.1/resources/main.js"))
+        );
+        assert_eq!(resolve(root, "/JetStream2.2/a%20b.js"), Some(root.join("JetStream2.2/a b.js")));
+        assert_eq!(resolve(root, "/../etc/passwd"), None);
+        assert_eq!(resolve(root, "/Speedometer2.1/%2e%2e/%2e%2e/etc/passwd"), None);
+        assert_eq!(resolve(root, "/bad%zz"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_out_of_the_root_are_not_followed() {
+        let dir = std::env::temp_dir().join(format!("mj-suites-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("suites");
+        std::fs::create_dir_all(root.join("JetStream2.2")).unwrap();
+        std::fs::write(root.join("JetStream2.2/cli.js"), "ok").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("secret.txt")).unwrap();
+        std::os::unix::fs::symlink(root.join("JetStream2.2"), root.join("JetStream")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        assert_eq!(respond(&root, "/JetStream2.2/cli.js").await.code, 200);
+        assert_eq!(respond(&root, "/JetStream/cli.js").await.code, 200);
+        assert_eq!(respond(&root, "/secret.txt").await.code, 404);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wasm_is_served_as_wasm() {
+        // JetStream compiles these with WebAssembly.instantiateStreaming.
+        assert_eq!(content_type(Path::new("JetStream2.2/wasm/hashset.wasm")), "application/wasm");
+        assert_eq!(content_type(Path::new("index.html")), "text/html; charset=utf-8");
+    }
+}
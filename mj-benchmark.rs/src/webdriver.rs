@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// MJ Benchmark
+// Copyright (c) 2024-2026
+// Mohamed Hammad
+//
+// Trademarks:
+//   Steelbore, S3cure, S3cure me, S3cure us, MJ Benchmark
+//   These names are trademarks of Mohamed Hammad and may not be used
+//   to endorse or promote derivative products without prior permission.
+
+//! WebDriver servers started and stopped by mj-benchmark.
+//!
+//! With `webdriver = "external"` (the default) the browser step connects
+//! to `webdriver_url` as before. Otherwise the driver binary is looked up
+//! on PATH, started on a free local port, and stopped when the step ends.
+
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Child;
+use tokio::time::{sleep, Instant};
+
+use crate::browser_bench::BrowserError;
+use crate::process::{self, GroupGuard, KILL_GRACE};
+
+/// How long a started driver gets to answer `/status` with ready.
+const READY_TIMEOUT: Duration = Duration::from_secs(15);
+const READY_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebDriverMode {
+    /// Connect to `webdriver_url`; something else runs the driver.
+    #[default]
+    External,
+    /// chromedriver if installed, else geckodriver.
+    Auto,
+    Chromedriver,
+    Geckodriver,
+}
+
+impl WebDriverMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebDriverMode::External => "external",
+            WebDriverMode::Auto => "auto",
+            WebDriverMode::Chromedriver => "chromedriver",
+            WebDriverMode::Geckodriver => "geckodriver",
+        }
+    }
+
+    /// Drivers to try, in order; empty for `External`.
+    fn candidates(self) -> &'static [Driver] {
+        match self {
+            WebDriverMode::External => &[],
+            WebDriverMode::Auto => &[Driver::Chromedriver, Driver::Geckodriver],
+            WebDriverMode::Chromedriver => &[Driver::Chromedriver],
+            WebDriverMode::Geckodriver => &[Driver::Geckodriver],
+        }
+    }
+}
+
+impl FromStr for WebDriverMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "external" => Ok(WebDriverMode::External),
+            "auto" => Ok(WebDriverMode::Auto),
+            "chromedriver" => Ok(WebDriverMode::Chromedriver),
+            "geckodriver" => Ok(WebDriverMode::Geckodriver),
+            other => Err(format!(
+                "unknown webdriver '{}' (expected external, auto, chromedriver or geckodriver)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Driver {
+    Chromedriver,
+    Geckodriver,
+}
+
+impl Driver {
+    fn binary(self) -> &'static str {
+        match self {
+            Driver::Chromedriver => "chromedriver",
+            Driver::Geckodriver => "geckodriver",
+        }
+    }
+
+    fn port_args(self, port: u16) -> Vec<String> {
+        match self {
+            Driver::Chromedriver => vec![format!("--port={}", port)],
+            Driver::Geckodriver => vec!["--port".into(), port.to_string()],
+        }
+    }
+}
+
+/// The driver binary `mode` would start, if one is installed.
+pub fn find(mode: WebDriverMode) -> Option<PathBuf> {
+    find_driver(mode).map(|(_, path)| path)
+}
+
+fn find_driver(mode: WebDriverMode) -> Option<(Driver, PathBuf)> {
+    mode.candidates()
+        .iter()
+        .find_map(|&driver| Some((driver, which::which(driver.binary()).ok()?)))
+}
+
+/// A running driver; stopped by [`ManagedDriver::stop`], or on drop when
+/// the step fails or is cancelled.
+pub struct ManagedDriver {
+    // Dropped first: the browsers it started get SIGTERM before
+    // kill_on_drop takes the driver down.
+    guard: GroupGuard,
+    child: Child,
+    url: String,
+    version: Option<String>,
+}
+
+impl ManagedDriver {
+    /// Start the driver `mode` names and wait until it accepts sessions.
+    pub async fn start(mode: WebDriverMode) -> Result<Self, BrowserError> {
+        let names = || {
+            mode.candidates()
+                .iter()
+                .map(|d| d.binary())
+                .collect::<Vec<_>>()
+                .join(" or ")
+        };
+        let (driver, path) = find_driver(mode).ok_or_else(|| BrowserError::DriverNotFound(names()))?;
+        let failed = |reason: String| BrowserError::DriverStart(path.display().to_string(), reason);
+
+        let port = free_port().map_err(|e| failed(format!("no free local port: {}", e)))?;
+        let version = driver_version(&path).await;
+        let mut child = process::command(&path)
+            .args(driver.port_args(port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| failed(e.to_string()))?;
+        let guard = GroupGuard::new(&child);
+        let url = format!("http://127.0.0.1:{}", port);
+
+        let started = Instant::now();
+        loop {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(failed(format!("exited with {} before it was ready", status)));
+            }
+            if is_ready(&url).await {
+                break;
+            }
+            if started.elapsed() >= READY_TIMEOUT {
+                return Err(failed(format!(
+                    "not ready on {} after {}s",
+                    url,
+                    READY_TIMEOUT.as_secs()
+                )));
+            }
+            sleep(READY_POLL).await;
+        }
+
+        Ok(Self {
+            guard,
+            child,
+            url,
+            version,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// First line of `<driver> --version`, e.g. "geckodriver 0.34.0 (…)",
+    /// for drivers whose `/status` has no version.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Stop the driver and any browser it left running.
+    pub async fn stop(mut self) {
+        self.guard.terminate();
+        if tokio::time::timeout(KILL_GRACE, self.child.wait()).await.is_err() {
+            let _ = self.child.kill().await;
+        }
+    }
+}
+
+/// A port that was free a moment ago; the driver binds it right after.
+fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+async fn is_ready(url: &str) -> bool {
+    let Ok(response) = reqwest::get(format!("{}/status", url)).await else {
+        return false;
+    };
+    let Ok(status) = response.json::<serde_json::Value>().await else {
+        return false;
+    };
+    status["value"]["ready"].as_bool().unwrap_or(false)
+}
+
+async fn driver_version(path: &std::path::Path) -> Option<String> {
+    let out = process::output(process::command(path).arg("--version"))
+        .await
+        .ok()?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}